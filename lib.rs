@@ -8,23 +8,216 @@ mod raffle {
     #[cfg(not(feature = "ink-as-dependency"))]
     use ink_storage::collections::{HashMap as StorageHashMap, Vec as StorageVec};
 
-    use ink_env::hash::Blake2x128;
+    use ink_env::call::{build_call, utils::ReturnType, ExecutionInput, Selector};
+    use ink_env::hash::{Blake2x128, Blake2x256};
+    use ink_prelude::vec::Vec;
+    #[cfg(feature = "std")]
+    use ink_storage::traits::StorageLayout;
+    use ink_storage::traits::{PackedLayout, SpreadLayout};
 
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
     /// to add new static storage fields to your contract.
     #[ink(storage)]
     pub struct Raffle {
-        /// The collected money would be sent to `beneficiary` when the second winner is drawn.
+        /// The account allowed to configure the raffle.
+        owner: AccountId,
+        /// The account that may accept ownership.
+        pending_owner: Option<AccountId>,
+        /// The collected money would be paid to `beneficiary` when the second winner is drawn.
         beneficiary: AccountId,
         /// Participants.
         participants: StorageHashMap<AccountId, Balance>,
         /// Winner candidates.
         candidates: StorageVec<AccountId>,
+        /// Membership set of `candidates`.
+        candidate_set: StorageHashMap<AccountId, ()>,
         /// The winners.
         winners: StorageVec<AccountId>,
         /// The time when the raffle could be drawn.
         draw_starts_at: Timestamp,
+        /// The time when the raffle was deployed.
+        created_at: Timestamp,
+        /// The block the raffle was deployed in.
+        created_at_block: BlockNumber,
+        /// Donations collected outside of raffle entries.
+        donations: Balance,
+        /// The PSP22 token entries are paid in, `None` for the native currency.
+        entry_token: Option<AccountId>,
+        /// Reject entries once the draw window has opened.
+        freeze_entries_at_draw_time: bool,
+        /// Fail draws instead of falling back when randomness is unavailable.
+        strict_randomness: bool,
+        /// Whether any draw this round used the deterministic fallback seed.
+        used_fallback: bool,
+        /// Rounds that finished and paid out the beneficiary.
+        rounds_completed: u32,
+        /// The current raffle round.
+        round: u32,
+        /// Merkle root of the winners published by the owner.
+        winner_root: Option<[u8; 32]>,
+        /// The last time each account withdrew its entry.
+        last_withdraw_at: StorageHashMap<AccountId, Timestamp>,
+        /// How long an account has to wait after withdrawing before playing again.
+        reentry_cooldown: Timestamp,
+        /// Maximum number of participants, if capped.
+        max_participants: Option<u32>,
+        /// Registry notified when the raffle finishes.
+        registry: Option<AccountId>,
+        /// Whether drawing is paused while entries stay open.
+        draw_paused: bool,
+        /// Whether non-essential events are emitted.
+        emit_events: bool,
+        /// Layout version of this storage, see `storage_version`.
+        storage_version: u32,
+        /// Draw a winner inline once any `play` or `poke` sees the draw is permitted.
+        auto_draw: bool,
+        /// Set while a draw is in progress.
+        drawing: bool,
+        /// Transfer the payout to the beneficiary when the raffle finishes.
+        push_payout: bool,
+        /// Payout the beneficiary has yet to withdraw.
+        pending_withdrawal: Balance,
+        /// Payout in the entry token the beneficiary has yet to withdraw.
+        pending_token_withdrawal: Balance,
+        /// The owner's commitment to a seed mixed into the draws.
+        seed_commitment: Option<[u8; 32]>,
+        /// The revealed preimage of `seed_commitment`.
+        revealed_seed: Option<[u8; 32]>,
+        /// Optional display names of participants.
+        names: StorageHashMap<AccountId, Vec<u8>>,
+        /// Keep `candidates` in entry order when removing one.
+        stable_removal: bool,
+        /// Contract providing randomness instead of the chain.
+        randomness_oracle: Option<AccountId>,
+        /// Total paid out to beneficiaries over all rounds.
+        lifetime_donated: Balance,
+        /// Never draw the participants with the largest deposit.
+        exclude_top_donor_from_winning: bool,
+        /// The time the last winner was drawn.
+        finished_at: Option<Timestamp>,
+        /// Entropy mixed from every entry into the draw seed.
+        entropy_accumulator: [u8; 32],
+        /// Accounts that opted out of playing.
+        self_excluded: StorageHashMap<AccountId, ()>,
+        /// Whether the minimum player count was reached this round.
+        threshold_reached: bool,
+        /// Deposits that close entries once reached, if capped.
+        pot_cap: Option<Balance>,
+        /// Inputs of every draw this round, see `verify_draw`.
+        draw_records: StorageVec<DrawRecord>,
+        /// Deposits required for the raffle to be drawn, if any.
+        soft_cap: Option<Balance>,
+        /// Whether participants can claim their deposits back instead of a draw.
+        refunds_enabled: bool,
+        /// Whether the beneficiary confirmed receiving this round's payout.
+        beneficiary_ack: bool,
+        /// Every entry of this round with its time, in entry order.
+        entry_feed: StorageVec<(AccountId, Timestamp)>,
+        /// Players required before the draw countdown starts.
+        min_players: u32,
+        /// Tickets held by each participant, one per `MIN_DEPOSIT` deposited.
+        tickets: StorageHashMap<AccountId, u32>,
+        /// How candidates are weighted in a draw.
+        weighting: Weighting,
+        /// Refunds `cancel` could not transfer, left for the accounts to retry.
+        failed_refunds: StorageHashMap<AccountId, Balance>,
+        /// Whether the candidates are shuffled once before the first draw of a round.
+        shuffle_before_draw: bool,
+        shuffled: bool,
+        /// Winners of every finished round, by round.
+        round_winners: StorageHashMap<u32, Vec<AccountId>>,
+        /// Balance payouts leave in the contract so it is never reaped.
+        reserve: Balance,
+        /// Time that has to pass between two draws.
+        draw_gap: Timestamp,
+        /// Whether the owner has to close entries before the first draw.
+        manual_close: bool,
+        entries_closed: bool,
+        /// Hidden entries awaiting their reveal, with the stake and round they were made in.
+        entry_commitments: StorageHashMap<AccountId, ([u8; 32], Balance, u32)>,
+        /// Whether unrevealed stakes go to the beneficiary instead of back to their owner.
+        forfeit_unrevealed_entries: bool,
+        /// Most an account may deposit over the round, across all its entries.
+        max_contribution_per_account: Option<Balance>,
+        /// What each account has deposited this round, withdrawals included.
+        contributions: StorageHashMap<AccountId, Balance>,
+        /// What happens when the beneficiary is drawn.
+        winner_beneficiary_policy: WinnerBeneficiaryPolicy,
+        /// Everything refunded to each account, across all rounds.
+        refunded: StorageHashMap<AccountId, Balance>,
+        /// Draws allowed within one block.
+        max_draws_per_block: u32,
+        /// The block of the latest draw and the draws made in it.
+        block_draws: (BlockNumber, u32),
+        /// Every surplus `play_with_refund_to` returned, with the account that overpaid.
+        overpayment_log: StorageVec<(AccountId, Balance)>,
+        /// Group tags given on entry, such as a team.
+        tags: StorageHashMap<AccountId, u32>,
+        /// Whether at most one account per tag can win.
+        one_winner_per_tag: bool,
+        /// How long after the draw window opens refunds unlock if the raffle is still unfinished.
+        refund_deadline: Option<Timestamp>,
+        /// Basis points of each finished pot kept to seed the next round.
+        rollover_bps: u16,
+        /// Kept from the last finished pot, credited as a donation on `reset`.
+        rollover_balance: Balance,
+        /// Unit deposits have to be a multiple of, 0 or 1 for any amount.
+        deposit_increment: Balance,
+        /// The pot and donations at each draw of the round, in draw order.
+        pot_at_draw: StorageVec<Balance>,
+        /// Whether a deposit of exactly the minimum or maximum is valid.
+        bounds_inclusive: bool,
+        /// Contract told about each winner as they are drawn.
+        notifier: Option<AccountId>,
+        /// Whether the pool and its weights are fixed once the first draw is made.
+        snapshot_on_first_draw: bool,
+        /// What winners receive, the pot goes to the beneficiary either way.
+        prize_mode: PrizeMode,
+        /// Prize tokens whose mint failed, by round and winner, with their
+        /// collection and id, left for the winner to claim.
+        pending_prizes: StorageHashMap<(u32, AccountId), (AccountId, u128)>,
+        /// How long an account has to wait between entry actions, zero for no limit.
+        action_cooldown: Timestamp,
+        /// The last time each account entered, increased or withdrew its deposit.
+        last_action_at: StorageHashMap<AccountId, Timestamp>,
+        /// Whether the owner settles the raffle with `finalize` after the last draw.
+        explicit_finalize: bool,
+        /// Whether every winner is drawn and the raffle waits for `finalize`.
+        awaiting_finalize: bool,
+        /// Number of entries this round.
+        plays_count: u64,
+        /// Sum of the opening deposits of this round's entries.
+        total_played_volume: Balance,
+        /// Number of entries over all rounds.
+        lifetime_plays_count: u64,
+        /// Sum of the opening deposits over all rounds.
+        lifetime_played_volume: Balance,
+        /// Account proposed to become the beneficiary, if any.
+        pending_beneficiary: Option<AccountId>,
+        /// Whether the beneficiary, rather than the owner, proposes its successor.
+        beneficiary_controls_handoff: bool,
+        /// The block of the latest entry and the entries made in it.
+        block_entries: (BlockNumber, u32),
+        /// Smallest deposit that can win, smaller deposits only fund the beneficiary.
+        min_winning_deposit: Balance,
+        /// Pot thresholds, ascending, each releasing basis points of itself early.
+        milestones: StorageVec<(Balance, u16)>,
+        /// How many milestones were released this round.
+        milestones_released: u32,
+        /// Balance released to the beneficiary through milestones this round.
+        milestone_released_balance: Balance,
+        /// Charities entries can vote for, the most voted one receives the pot.
+        charities: StorageVec<AccountId>,
+        /// The charity index each entry voted for.
+        charity_votes: StorageHashMap<AccountId, u32>,
+        /// The charity that received this round's payout, `None` for the beneficiary.
+        round_recipient: Option<AccountId>,
+        /// Payout `round_recipient` has yet to withdraw.
+        recipient_pending: Balance,
+        /// How long after the draw window opens a committed seed may stay
+        /// unrevealed before `draw_fallback` is allowed, zero for never.
+        reveal_timeout: Timestamp,
     }
 
     #[ink(event)]
@@ -37,6 +230,83 @@ mod raffle {
         balance: Balance,
     }
 
+    #[ink(event)]
+    pub struct DepositIncreased {
+        /// Who increased the deposit.
+        #[ink(topic)]
+        who: AccountId,
+        /// The deposit after the increase.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        /// Who withdrew the entry.
+        #[ink(topic)]
+        who: AccountId,
+        /// Refunded balance.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Donated {
+        /// Who donated.
+        #[ink(topic)]
+        who: AccountId,
+        /// Donated balance.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ThresholdReached {
+        /// When the minimum player count was reached.
+        #[ink(topic)]
+        at: Timestamp,
+        /// The number of players at that time.
+        #[ink(topic)]
+        players: u32,
+    }
+
+    #[ink(event)]
+    pub struct RefundsEnabled {
+        /// Deposits when refunds were enabled.
+        #[ink(topic)]
+        pot: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Refunded {
+        /// Who was refunded.
+        #[ink(topic)]
+        who: AccountId,
+        /// Refunded balance.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct DrawGapChanged {
+        /// The new time between two draws.
+        gap: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct MinPlayersChanged {
+        /// The new minimum player count.
+        #[ink(topic)]
+        min_players: u32,
+    }
+
+    #[ink(event)]
+    pub struct WinForfeited {
+        /// The beneficiary whose pick was forfeited.
+        #[ink(topic)]
+        who: AccountId,
+    }
+
     #[ink(event)]
     pub struct Draw {
         /// The winner of this draw.
@@ -54,6 +324,115 @@ mod raffle {
         balance: Balance,
     }
 
+    #[ink(event)]
+    pub struct TokenPaidOut {
+        /// The entry token.
+        #[ink(topic)]
+        token: AccountId,
+        /// The beneficiary.
+        #[ink(topic)]
+        beneficiary: AccountId,
+        /// Total tokens sent.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct MilestoneReleased {
+        /// Milestones released so far this round.
+        released: u32,
+        /// Balance sent to the beneficiary.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct PayoutWithdrawn {
+        /// The beneficiary.
+        #[ink(topic)]
+        beneficiary: AccountId,
+        /// Withdrawn balance.
+        #[ink(topic)]
+        balance: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ReceiptAcknowledged {
+        /// When the beneficiary acknowledged the payout.
+        #[ink(topic)]
+        at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct TokenRescued {
+        /// The rescued PSP22 token.
+        #[ink(topic)]
+        token: AccountId,
+        /// Who received the tokens.
+        #[ink(topic)]
+        to: AccountId,
+        /// Rescued amount.
+        #[ink(topic)]
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NewRound {
+        /// The round that started.
+        #[ink(topic)]
+        round: u32,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferStarted {
+        /// The current owner.
+        #[ink(topic)]
+        owner: AccountId,
+        /// The account that may accept ownership.
+        #[ink(topic)]
+        pending_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferCancelled {
+        /// The current owner.
+        #[ink(topic)]
+        owner: AccountId,
+        /// The account that can no longer accept ownership.
+        #[ink(topic)]
+        pending_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        /// The previous owner.
+        #[ink(topic)]
+        previous_owner: AccountId,
+        /// The new owner.
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct BeneficiaryTransferStarted {
+        /// The current beneficiary.
+        #[ink(topic)]
+        beneficiary: AccountId,
+        /// The account that may accept the beneficiary role.
+        #[ink(topic)]
+        pending_beneficiary: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct BeneficiaryChanged {
+        /// The previous beneficiary.
+        #[ink(topic)]
+        previous_beneficiary: AccountId,
+        /// The new beneficiary.
+        #[ink(topic)]
+        new_beneficiary: AccountId,
+    }
+
     #[derive(Debug, PartialEq, Eq, scale::Encode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
@@ -61,114 +440,6802 @@ mod raffle {
         InvalidDepositAmount,
         /// The player has already played.
         HasPlayed,
-        /// Two winners drawn, raffle finished.
+        /// All winners drawn, raffle finished.
         Finished,
         /// Draw is not started yet.
         DrawNotStarted,
         /// Minimum player count not reached.
         NotEnoughPlayer,
+        /// Donations must transfer a non-zero balance.
+        InvalidDonationAmount,
+        /// Entries of this raffle are paid in its entry token.
+        EntryTokenRequired,
+        /// This raffle takes entries in the native currency.
+        NoEntryToken,
+        /// The PSP22 token refused or failed the transfer.
+        TokenTransferFailed,
+        /// Only the owner is allowed to do this.
+        NotOwner,
+        /// The draw window has opened, or the pool was fixed by the first draw,
+        /// and entries are frozen.
+        EntriesFrozen,
+        /// The chain returned no usable randomness.
+        RandomnessUnavailable,
+        /// The caller has not played.
+        HasNotPlayed,
+        /// The caller withdrew too recently to play again.
+        ReentryCooldown,
+        /// Not allowed once the first winner has been drawn.
+        DrawStarted,
+        /// The balance transfer failed.
+        TransferFailed,
+        /// The maximum participant count has been reached.
+        RaffleFull,
+        /// Drawing is paused by the owner.
+        DrawPaused,
+        /// A draw is already in progress.
+        DrawInProgress,
+        /// The caller is not the pending owner.
+        NotPendingOwner,
+        /// No ownership transfer is pending.
+        NoPendingOwner,
+        /// Only the beneficiary is allowed to do this.
+        NotBeneficiary,
+        /// There is no pending payout to withdraw.
+        NothingToWithdraw,
+        /// A seed has already been committed.
+        SeedAlreadyCommitted,
+        /// Seeds must be committed before the draw window opens.
+        CommitClosed,
+        /// No seed has been committed.
+        NoSeedCommitted,
+        /// The seed does not match the commitment.
+        InvalidSeed,
+        /// The committed seed must be revealed before drawing.
+        SeedNotRevealed,
+        /// The display name is longer than allowed.
+        NameTooLong,
+        /// The display name is not valid UTF-8.
+        InvalidName,
+        /// The raffle has not finished yet.
+        NotFinished,
+        /// Every candidate has already been drawn.
+        NoCandidates,
+        /// The entry token holds participant deposits and can't be rescued.
+        CannotRescueEntryToken,
+        /// The eligibility rules leave no candidate that could win.
+        NoEligibleCandidates,
+        /// The beneficiary has not withdrawn the payout yet.
+        PayoutPending,
+        /// The account opted out of playing.
+        SelfExcluded,
+        /// The deposit would exceed the pot cap.
+        PotCapReached,
+        /// The raffle is refunding deposits instead of drawing.
+        RefundsEnabled,
+        /// Refunds are not enabled.
+        RefundsNotEnabled,
+        /// The batch is empty or larger than allowed.
+        InvalidBatchSize,
+        /// The minimum player count is out of range.
+        InvalidMinPlayers,
+        /// The call was meant for a different round.
+        WrongRound,
+        /// The gap since the previous draw has not passed yet.
+        DrawTooSoon,
+        /// The draw gap is longer than allowed.
+        InvalidDrawGap,
+        /// An account appears more than once in a batch.
+        DuplicateInBatch,
+        /// The owner has closed entries.
+        EntriesClosed,
+        /// Entries have to be closed before drawing.
+        EntriesOpen,
+        /// The caller already has a hidden entry waiting to be revealed.
+        EntryAlreadyCommitted,
+        /// There is no hidden entry for the account.
+        NoEntryCommitted,
+        /// The nonce does not match the hidden entry.
+        InvalidEntryReveal,
+        /// Hidden entries of this round can still be revealed.
+        RevealOpen,
+        /// The account would deposit more than allowed over the round.
+        ContributionCapExceeded,
+        /// The zero address can't receive refunds.
+        InvalidRefundAddress,
+        /// This block already had as many draws as allowed.
+        DrawRateLimited,
+        /// At least one draw per block has to be allowed.
+        InvalidDrawRateLimit,
+        /// The amount is larger than the pending payout.
+        AmountExceedsPending,
+        /// The refund deadline is not set or has not passed.
+        RefundDeadlineNotReached,
+        /// The account already has an entry, which would have been overwritten.
+        DuplicateEntry,
+        /// A share is larger than 10000 basis points.
+        InvalidBps,
+        /// The setting is not available for a raffle paid in an entry token.
+        UnsupportedForEntryToken,
+        /// The amount is not a multiple of the deposit increment.
+        InvalidIncrement,
+        /// The prize collection refused to mint the token.
+        PrizeMintFailed,
+        /// There is no prize waiting for the caller.
+        NoPendingPrize,
+        /// The drawn account is not a valid candidate, the draw was not committed.
+        StateInconsistent,
+        /// The account acted again within the action cooldown.
+        ActionTooSoon,
+        /// Every winner is drawn, the raffle waits for `finalize`.
+        AwaitingFinalize,
+        /// The raffle is not waiting for `finalize`.
+        NotAwaitingFinalize,
+        /// The caller is not the pending beneficiary.
+        NotPendingBeneficiary,
+        /// There is no pending beneficiary transfer.
+        NoPendingBeneficiary,
+        /// There is no candidate charity at this index.
+        InvalidCharity,
+        /// The committed seed hasn't been left unrevealed past the reveal timeout.
+        RevealNotTimedOut,
+        /// Milestone thresholds must ascend and their shares sum to at most 10000.
+        InvalidMilestones,
+        /// The pot hasn't crossed the next milestone.
+        MilestoneNotReached,
+        /// Part of the pot was already released, so deposits can't be returned.
+        MilestoneReleased,
     }
 
     pub type Result<T> = core::result::Result<T, Error>;
 
+    /// Error returned by a PSP22 token, mirroring the standard's variants.
+    #[derive(Debug, PartialEq, Eq, scale::Decode)]
+    enum Psp22Error {
+        Custom(Vec<u8>),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(Vec<u8>),
+    }
+
+    /// Aggregate counters returned by `stats`.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct RaffleStats {
+        /// Accounts that have played.
+        unique_participants: u32,
+        /// Tickets held across all participants.
+        total_tickets: u32,
+        /// Sum of all deposits.
+        total_pot: Balance,
+        /// Sum of all donations.
+        total_donations: Balance,
+        /// Winners drawn so far.
+        winners_drawn: u32,
+        /// Rounds that have finished.
+        rounds_completed: u32,
+    }
+
+    /// The inputs of a draw, kept so anyone can recompute its winner.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+    pub struct DrawRecord {
+        /// Block timestamp of the draw.
+        timestamp: Timestamp,
+        /// Block number of the draw.
+        block_number: BlockNumber,
+        /// The randomness the winner was picked with.
+        randomness: Hash,
+        /// The candidates that were eligible, in selection order.
+        pool: Vec<AccountId>,
+        /// The weight of each pool entry, empty when odds were equal.
+        weights: Vec<u32>,
+        /// Whether the randomness came from the deterministic fallback seed.
+        used_fallback: bool,
+        /// The candidate picked, a winner unless the pick was forfeited.
+        drawn: AccountId,
+    }
+
+    /// A page of raffle state for archiving off chain.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Snapshot {
+        /// Layout version of the contract storage.
+        storage_version: u32,
+        owner: AccountId,
+        beneficiary: AccountId,
+        round: u32,
+        min_players: u32,
+        max_participants: Option<u32>,
+        pot_cap: Option<Balance>,
+        soft_cap: Option<Balance>,
+        donations: Balance,
+        /// Participants across all pages.
+        participant_count: u32,
+        /// The requested page of participants with their deposits.
+        participants: Vec<(AccountId, Balance)>,
+        /// Winners drawn so far, in draw order.
+        winners: Vec<AccountId>,
+    }
+
+    /// Hash function the draw seed is built with.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum HashAlgorithm {
+        Blake2x128,
+    }
+
+    /// The trust-relevant facts about how winners are drawn.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct FairnessReport {
+        /// Hash function the draw seed is built with.
+        hash_algorithm: HashAlgorithm,
+        /// Whether any draw this round used the deterministic fallback seed.
+        used_fallback: bool,
+        /// Whether randomness is fetched from an oracle contract.
+        randomness_oracle: bool,
+        /// Whether a committed seed is mixed into the draws.
+        commit_reveal: bool,
+        /// Draws made this round.
+        draws: u32,
+        /// How candidates are weighted in a draw.
+        weighting: Weighting,
+    }
+
+    /// How candidates are weighted in a draw.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+    pub enum Weighting {
+        /// Every candidate has the same odds.
+        Equal,
+        /// Odds are proportional to the tickets held.
+        Tickets,
+        /// Odds are proportional to the integer square root of the deposit, so
+        /// larger deposits win more often without dominating the draw.
+        SqrtDeposit,
+    }
+
+    /// What happens when the beneficiary, who may also play, is drawn. Candidate
+    /// charities that play are treated the same.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+    pub enum WinnerBeneficiaryPolicy {
+        /// The beneficiary wins like anyone else.
+        Allow,
+        /// The beneficiary leaves the pool and the draw has no winner.
+        Forfeit,
+        /// The draw is made again, which is the same as leaving the beneficiary
+        /// out of the pool.
+        Reroll,
+    }
+
+    /// What winners receive when the raffle finishes.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode, SpreadLayout, PackedLayout)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+    pub enum PrizeMode {
+        /// Winners receive nothing.
+        NoPrize,
+        /// Each winner is minted a token of the PSP34 `collection`.
+        Nft { collection: AccountId },
+    }
+
+    /// How a finished pot is split, in basis points summing to 10000.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Splits {
+        /// Kept to seed the next round.
+        rollover_bps: u16,
+        /// Paid to the beneficiary, everything not kept.
+        beneficiary_bps: u16,
+    }
+
+    /// How the deposits compare to the soft cap and pot cap.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum CapStatus {
+        /// Deposits are below the soft cap.
+        Below,
+        /// The soft cap, if any, is met and entries are open.
+        Met,
+        /// The pot cap is reached and entries are closed.
+        Reached,
+    }
+
+    /// Where the round's seed commitment is in its commit-reveal lifecycle.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RngStatus {
+        /// No seed is committed, draws use the chain randomness alone.
+        NotCommitted,
+        /// A seed is committed and draws wait for it to be revealed.
+        Committed { hash: [u8; 32] },
+        /// The committed seed is revealed and mixed into every draw.
+        Revealed { seed: [u8; 32] },
+    }
+
+    /// Whether a draw is currently permitted, and why not.
+    #[derive(Debug, PartialEq, Eq, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum DrawStatus {
+        /// A draw can be made now.
+        Ready,
+        /// The draw window opens in `remaining` milliseconds.
+        InCountdown { remaining: Timestamp },
+        /// `needed` more players are required.
+        NotEnoughPlayers { needed: u32 },
+        /// All winners have been drawn.
+        Finished,
+        /// Drawing is paused by the owner.
+        Paused,
+        /// The round was cancelled or missed a cap, and refunds deposits.
+        Refunding,
+        /// A draw is refused for `reason`, such as an unrevealed seed.
+        Blocked { reason: Error },
+        /// All winners have been drawn and the owner has yet to `finalize`.
+        AwaitingFinalize,
+    }
+
+    /// Error returned by a PSP34 collection, mirroring the standard's variants.
+    #[derive(Debug, PartialEq, Eq, scale::Decode)]
+    enum Psp34Error {
+        Custom(Vec<u8>),
+        SelfApprove,
+        NotApproved,
+        TokenExists,
+        TokenNotExists,
+        SafeTransferCheckFailed(Vec<u8>),
+    }
+
+    /// Current storage layout version, bump it whenever the storage layout changes.
+    const STORAGE_VERSION: u32 = 1;
+
+    /// Version of the event shapes, bump it whenever an event gains, loses or
+    /// reorders a field or topic, so indexers know how to decode. Adding a new
+    /// event does not bump it.
+    const EVENT_SCHEMA_VERSION: u16 = 1;
+
+    const MIN_DEPOSIT: Balance = 10000000000000;
+    const MAX_DEPOSIT: Balance = 100000000000000;
     const DRAW_COUNTDOWN: Timestamp = 900000;
     const MINI_PLAYER_COUNT: u32 = 5;
+    const WINNER_COUNT: u32 = 2;
+    /// How far `set_min_players` may set the minimum above the current player count.
+    const MIN_PLAYERS_SLACK: u32 = 100;
+    /// Maximum number of accounts entered by one `play_for`.
+    const MAX_BATCH_SIZE: usize = 20;
+    /// Longest gap the owner can require between two draws.
+    const MAX_DRAW_GAP: Timestamp = DRAW_COUNTDOWN;
+    /// Basis points making up a whole pot.
+    const BPS_DENOMINATOR: u16 = 10000;
+    /// Maximum length of a display name in bytes.
+    const MAX_NAME_LEN: usize = 32;
+    /// Selector of the notifier's `notify_winner(who, index)` message.
+    const NOTIFY_WINNER_SELECTOR: [u8; 4] = [0xA2, 0xAC, 0x35, 0x9F];
+    /// Selector of `PSP34Mintable::mint(account, id)`, returning `Result<(), PSP34Error>`.
+    const PSP34_MINT_SELECTOR: [u8; 4] = [0x6C, 0x41, 0xF2, 0xEC];
+    /// Selector of `PSP22::transfer(to, value, data)`.
+    ///
+    /// The call returns `Result<(), PSP22Error>`, which is decoded so a refused
+    /// transfer fails too.
+    const PSP22_TRANSFER_SELECTOR: [u8; 4] = [0xDB, 0x20, 0xF9, 0xF5];
+    /// Selector of `PSP22::transfer_from(from, to, value, data)`, returning `Result<(), PSP22Error>`.
+    const PSP22_TRANSFER_FROM_SELECTOR: [u8; 4] = [0x54, 0xB3, 0xC7, 0x6E];
+    /// Selector of `PSP22::balance_of(owner)`.
+    const PSP22_BALANCE_OF_SELECTOR: [u8; 4] = [0x65, 0x68, 0x38, 0x2F];
+    /// Selector of the registry's `report_finished(winners, total_paid)` message.
+    const REPORT_FINISHED_SELECTOR: [u8; 4] = [0xB4, 0x9E, 0xAB, 0x94];
+    /// Selector of the randomness oracle's `get_random(seed) -> Hash` message.
+    const GET_RANDOM_SELECTOR: [u8; 4] = [0x70, 0x84, 0x8F, 0x67];
 
     impl Raffle {
         /// Constructor that initializes the `beneficiary` value to the given address.
         #[ink(constructor)]
         pub fn new(beneficiary: AccountId) -> Self {
             Self {
+                owner: Self::env().caller(),
+                pending_owner: None,
                 beneficiary,
                 participants: StorageHashMap::new(),
                 candidates: StorageVec::new(),
+                candidate_set: StorageHashMap::new(),
                 winners: StorageVec::new(),
                 draw_starts_at: 0,
+                created_at: Self::env().block_timestamp(),
+                created_at_block: Self::env().block_number(),
+                donations: 0,
+                entry_token: None,
+                freeze_entries_at_draw_time: false,
+                strict_randomness: false,
+                used_fallback: false,
+                rounds_completed: 0,
+                round: 0,
+                winner_root: None,
+                last_withdraw_at: StorageHashMap::new(),
+                reentry_cooldown: 0,
+                max_participants: None,
+                registry: None,
+                draw_paused: false,
+                emit_events: true,
+                storage_version: STORAGE_VERSION,
+                auto_draw: false,
+                drawing: false,
+                push_payout: false,
+                pending_withdrawal: 0,
+                pending_token_withdrawal: 0,
+                seed_commitment: None,
+                revealed_seed: None,
+                names: StorageHashMap::new(),
+                stable_removal: false,
+                randomness_oracle: None,
+                lifetime_donated: 0,
+                exclude_top_donor_from_winning: false,
+                finished_at: None,
+                entropy_accumulator: [0; 32],
+                self_excluded: StorageHashMap::new(),
+                threshold_reached: false,
+                pot_cap: None,
+                draw_records: StorageVec::new(),
+                soft_cap: None,
+                refunds_enabled: false,
+                beneficiary_ack: false,
+                entry_feed: StorageVec::new(),
+                min_players: MINI_PLAYER_COUNT,
+                tickets: StorageHashMap::new(),
+                weighting: Weighting::Equal,
+                failed_refunds: StorageHashMap::new(),
+                shuffle_before_draw: false,
+                shuffled: false,
+                round_winners: StorageHashMap::new(),
+                reserve: Self::env().minimum_balance(),
+                draw_gap: 0,
+                manual_close: false,
+                entries_closed: false,
+                entry_commitments: StorageHashMap::new(),
+                forfeit_unrevealed_entries: false,
+                max_contribution_per_account: None,
+                contributions: StorageHashMap::new(),
+                winner_beneficiary_policy: WinnerBeneficiaryPolicy::Reroll,
+                refunded: StorageHashMap::new(),
+                max_draws_per_block: WINNER_COUNT,
+                block_draws: (0, 0),
+                overpayment_log: StorageVec::new(),
+                tags: StorageHashMap::new(),
+                one_winner_per_tag: false,
+                refund_deadline: None,
+                rollover_bps: 0,
+                rollover_balance: 0,
+                deposit_increment: 0,
+                pot_at_draw: StorageVec::new(),
+                bounds_inclusive: true,
+                notifier: None,
+                snapshot_on_first_draw: false,
+                prize_mode: PrizeMode::NoPrize,
+                pending_prizes: StorageHashMap::new(),
+                action_cooldown: 0,
+                last_action_at: StorageHashMap::new(),
+                explicit_finalize: false,
+                awaiting_finalize: false,
+                plays_count: 0,
+                total_played_volume: 0,
+                lifetime_plays_count: 0,
+                lifetime_played_volume: 0,
+                pending_beneficiary: None,
+                beneficiary_controls_handoff: false,
+                block_entries: (0, 0),
+                min_winning_deposit: 0,
+                milestones: StorageVec::new(),
+                milestones_released: 0,
+                milestone_released_balance: 0,
+                charities: StorageVec::new(),
+                charity_votes: StorageHashMap::new(),
+                round_recipient: None,
+                recipient_pending: 0,
+                reveal_timeout: 0,
+            }
+        }
+
+        /// Constructor for a raffle whose entries are paid in the PSP22 `entry_token`.
+        ///
+        /// Donations stay in the native currency, so the beneficiary is paid the
+        /// deposits in the token and the donations natively.
+        #[ink(constructor)]
+        pub fn with_entry_token(beneficiary: AccountId, entry_token: AccountId) -> Self {
+            let mut raffle = Self::new(beneficiary);
+            raffle.entry_token = Some(entry_token);
+            raffle
+        }
+
+        /// Constructor that also sets the contract told about each winner.
+        #[ink(constructor)]
+        pub fn with_notifier(beneficiary: AccountId, notifier: Option<AccountId>) -> Self {
+            let mut raffle = Self::new(beneficiary);
+            raffle.notifier = notifier;
+            raffle
+        }
+
+        /// Creates a raffle whose entries vote for one of `charities` to receive the pot.
+        ///
+        /// The leading charity receives only the round's payout, `beneficiary`
+        /// keeps its role and receives the pot if no entry votes.
+        #[ink(constructor)]
+        pub fn with_charities(beneficiary: AccountId, charities: Vec<AccountId>) -> Self {
+            let mut raffle = Self::new(beneficiary);
+            for charity in charities.into_iter() {
+                raffle.charities.push(charity);
             }
+            raffle
         }
 
         fn finished(&self) -> bool {
-            self.winners.len() == 2
+            self.finished_at.is_some()
         }
 
-        #[ink(message)]
-        #[ink(payable)]
-        pub fn play(&mut self) -> Result<()> {
-            if self.finished() {
-                return Err(Error::Finished);
+        fn add_candidate(&mut self, who: AccountId) {
+            self.candidates.push(who);
+            self.candidate_set.insert(who, ());
+        }
+
+        fn remove_candidate(&mut self, who: AccountId) {
+            if self.is_candidate(who) {
+                if let Some(index) = self.candidates.iter().position(|c| *c == who) {
+                    self.remove_candidate_at(index as u32);
+                }
             }
+        }
 
-            let who = self.env().caller();
-            if self.participants.get(&who).is_some() {
-                return Err(Error::HasPlayed);
+        fn remove_candidate_at(&mut self, index: u32) {
+            let removed = if self.stable_removal {
+                for i in index..self.candidates.len().saturating_sub(1) {
+                    self.candidates.swap(i, i + 1);
+                }
+                self.candidates.pop()
+            } else {
+                self.candidates.swap_remove(index)
+            };
+
+            if let Some(who) = removed {
+                self.candidate_set.take(&who);
             }
+        }
 
-            let balance = self.env().transferred_balance();
-            if balance < 10000000000000 || balance > 100000000000000 {
-                return Err(Error::InvalidDepositAmount);
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
             }
+            Ok(())
+        }
 
-            self.participants.insert(who, balance);
-            self.candidates.push(who);
+        fn draw_window_open(&self) -> bool {
+            self.draw_starts_at != 0 && self.env().block_timestamp() >= self.draw_starts_at
+        }
+
+        /// Returns the randomness for the next draw and whether it is the fallback.
+        ///
+        /// The configured oracle is asked first, then the chain's `random()`. An
+        /// all-zero output is treated as unavailable. Unless `strict_randomness`
+        /// is set, the seed then falls back to a hash of the contract id, round,
+        /// block number, block timestamp, winner count, revealed seed and entry
+        /// entropy. Only the entry entropy is beyond what block producers can
+        /// predict; `used_fallback` records that this happened.
+        fn randomness(&mut self) -> Result<(Hash, bool)> {
+            let hashed_seed = self.draw_seed();
+
+            if let Some(oracle) = self.randomness_oracle {
+                match self.oracle_random(oracle, hashed_seed) {
+                    Some(rand) => return Ok((rand, false)),
+                    None if self.strict_randomness => return Err(Error::RandomnessUnavailable),
+                    None => {}
+                }
+            }
+
+            let rand = self.env().random(&hashed_seed[..]);
+            if rand.as_ref().iter().any(|b| *b != 0) {
+                return Ok((rand, false));
+            }
+
+            if self.strict_randomness {
+                return Err(Error::RandomnessUnavailable);
+            }
+
+            let fallback = (
+                self.env().account_id(),
+                self.round,
+                self.env().block_number(),
+                self.env().block_timestamp(),
+                self.winners.len(),
+                self.revealed_seed,
+                self.entropy_accumulator,
+            );
+            self.used_fallback = true;
+            Ok((Hash::from(self.env().hash_encoded::<Blake2x256, _>(&fallback)), true))
+        }
+
+        /// Returns the seed the next draw's randomness is requested with.
+        fn draw_seed(&self) -> [u8; 16] {
+            // the seed would unique even two valid draws in same block, and the
+            // contract id and round keep raffles sharing a chain independent.
+            let seed = (
+                self.env().account_id(),
+                self.round,
+                self.env().block_timestamp(),
+                self.winners.len(),
+                self.revealed_seed,
+                self.entropy_accumulator,
+            );
+            self.env().hash_encoded::<Blake2x128, _>(&seed)
+        }
+
+        /// Fetches randomness from `oracle`, `None` if the call fails or returns all zeros.
+        fn oracle_random(&self, oracle: AccountId, seed: [u8; 16]) -> Option<Hash> {
+            build_call::<Environment>()
+                .callee(oracle)
+                .exec_input(ExecutionInput::new(Selector::new(GET_RANDOM_SELECTOR)).push_arg(seed))
+                .returns::<ReturnType<Hash>>()
+                .fire()
+                .ok()
+                .filter(|rand| rand.as_ref().iter().any(|b| *b != 0))
+        }
 
-            if self.participants.len() == 5 {
-                self.draw_starts_at = Self::env().block_timestamp() + DRAW_COUNTDOWN;
+        /// Returns whether the randomness oracle answers with usable randomness,
+        /// always `true` without an oracle.
+        ///
+        /// The probe asks for randomness on a fixed seed, so it costs one
+        /// cross-contract call and changes nothing.
+        #[ink(message)]
+        pub fn oracle_healthy(&self) -> bool {
+            match self.randomness_oracle {
+                Some(oracle) => self.oracle_random(oracle, [0u8; 16]).is_some(),
+                None => true,
             }
+        }
+
+        /// Sets the contract draws fetch randomness from, `None` uses the chain's `random()`.
+        ///
+        /// If the oracle fails the draw falls back to `random()`, unless
+        /// `strict_randomness` is set.
+        #[ink(message)]
+        pub fn set_randomness_oracle(&mut self, oracle: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.randomness_oracle = oracle;
+            Ok(())
+        }
+
+        /// Sets whether draws fail with `RandomnessUnavailable` instead of
+        /// falling back to a deterministic seed.
+        #[ink(message)]
+        pub fn set_strict_randomness(&mut self, strict: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.strict_randomness = strict;
+            Ok(())
+        }
 
-            self.env().emit_event(Played { who, balance });
+        /// Returns whether any draw this round used the deterministic fallback seed.
+        #[ink(message)]
+        pub fn used_fallback(&self) -> bool {
+            self.used_fallback
+        }
 
+        /// Sets whether the owner has to `close_entries` before the first draw.
+        #[ink(message)]
+        pub fn set_manual_close(&mut self, manual_close: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.manual_close = manual_close;
             Ok(())
         }
 
+        /// Stops accepting entries for the rest of the round.
         #[ink(message)]
-        pub fn draw(&mut self) -> Result<()> {
+        pub fn close_entries(&mut self) -> Result<()> {
+            self.ensure_owner()?;
             if self.finished() {
                 return Err(Error::Finished);
             }
+            self.entries_closed = true;
+            Ok(())
+        }
 
-            if self.draw_starts_at == 0 || self.env().block_timestamp() < self.draw_starts_at {
-                return Err(Error::DrawNotStarted);
-            }
+        /// Closes entries and draws the first winner in one call.
+        ///
+        /// Entries stay open if the draw can't be made.
+        #[ink(message)]
+        pub fn close_and_draw(&mut self) -> Result<()> {
+            self.ensure_owner()?;
 
-            if self.participants.len() < MINI_PLAYER_COUNT {
-                return Err(Error::NotEnoughPlayer);
+            let was_closed = self.entries_closed;
+            self.entries_closed = true;
+            let result = self.draw(None);
+            if result.is_err() {
+                self.entries_closed = was_closed;
             }
+            result
+        }
 
-            // the seed would unique even two valid draws in same block.
-            let seed = (self.env().block_timestamp(), self.winners.len());
-            let hashed_seed = self.env().hash_encoded::<Blake2x128, _>(&seed);
-            let mut rand = self.env().random(&hashed_seed[..]);
-            let rand_int = rand.as_mut().iter().fold(0u8, |acc, r| acc ^ r);
-
-            let winner_index = rand_int as u32 % self.candidates.len();
-            let winner = self.candidates[winner_index];
+        #[ink(message)]
+        pub fn entries_closed(&self) -> bool {
+            self.entries_closed
+        }
 
-            self.winners.push(self.candidates[winner_index]);
-            self.candidates.swap_remove_drop(winner_index);
+        /// Sets the most an account may deposit over the round, `None` removes it.
+        ///
+        /// Unlike the per-entry maximum deposit, this adds up every entry and
+        /// increase of the account, even ones it has since withdrawn.
+        #[ink(message)]
+        pub fn set_max_contribution_per_account(&mut self, max: Option<Balance>) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_contribution_per_account = max;
+            Ok(())
+        }
 
-            self.env().emit_event(Draw { winner });
+        /// Sets whether `play` is rejected once the draw window has opened.
+        #[ink(message)]
+        pub fn set_freeze_entries_at_draw_time(&mut self, freeze: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.freeze_entries_at_draw_time = freeze;
+            Ok(())
+        }
 
-            if self.finished() {
-                // transfer all balances
-                let balance = self.participants.iter().fold(0, |acc, p| acc + p.1);
-                let _ = self.env().transfer(self.beneficiary, balance);
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn play(&mut self) -> Result<()> {
+            let who = self.env().caller();
+            let balance = self.native_deposit()?;
+            self.ensure_can_enter(who, balance)?;
 
-                self.env().emit_event(Finished { beneficiary: self.beneficiary, balance });
-            }
+            self.enter(who, balance)?;
+            self.maybe_auto_draw();
 
             Ok(())
         }
 
+        /// Plays with up to the maximum deposit and returns any surplus to `refund_to`.
+        ///
+        /// A transfer above the maximum deposit is accepted, the part above it is
+        /// sent to `refund_to`, which may differ from the caller when the caller
+        /// can't receive transfers.
         #[ink(message)]
-        pub fn winners(&self) -> (Option<AccountId>, Option<AccountId>) {
-            let first = self.winners.first().map(|w| w.clone());
-            let last = self.winners.last().map(|w| w.clone());
-            return (first, last)
+        #[ink(payable)]
+        pub fn play_with_refund_to(&mut self, refund_to: AccountId) -> Result<()> {
+            if refund_to == AccountId::from([0u8; 32]) {
+                return Err(Error::InvalidRefundAddress);
+            }
+
+            let who = self.env().caller();
+            let transferred = self.native_deposit()?;
+            let balance = core::cmp::min(transferred, self.max_valid_deposit());
+            self.ensure_can_enter(who, balance)?;
+
+            let surplus = transferred - balance;
+            if surplus > 0 {
+                if self.env().transfer(refund_to, surplus).is_err() {
+                    return Err(Error::TransferFailed);
+                }
+                self.overpayment_log.push((who, surplus));
+            }
+
+            self.enter(who, balance)?;
+            self.maybe_auto_draw();
+
+            Ok(())
         }
 
+        /// Enters every account in `accounts`, splitting the transferred balance evenly.
+        ///
+        /// The balance must split into equal valid deposits, and each account must
+        /// be able to play on its own, so an account can't appear twice. At most
+        /// `MAX_BATCH_SIZE` accounts are accepted.
         #[ink(message)]
-        pub fn beneficiary(&self) -> AccountId {
-            self.beneficiary
+        #[ink(payable)]
+        pub fn play_for(&mut self, accounts: Vec<AccountId>) -> Result<()> {
+            if accounts.is_empty() || accounts.len() > MAX_BATCH_SIZE {
+                return Err(Error::InvalidBatchSize);
+            }
+
+            let count = accounts.len() as u32;
+            let balance = self.native_deposit()?;
+            if !balance.is_multiple_of(count as Balance) {
+                return Err(Error::InvalidDepositAmount);
+            }
+            let share = balance / count as Balance;
+
+            if matches!(self.pot_remaining_to_cap(), Some(remaining) if balance > remaining) {
+                return Err(Error::PotCapReached);
+            }
+
+            if matches!(self.slots_remaining(), Some(remaining) if remaining < count) {
+                return Err(Error::RaffleFull);
+            }
+
+            for (i, who) in accounts.iter().enumerate() {
+                if accounts[..i].contains(who) {
+                    return Err(Error::DuplicateInBatch);
+                }
+                self.ensure_can_enter(*who, share)?;
+            }
+
+            for who in accounts.iter() {
+                self.enter(*who, share)?;
+            }
+            self.maybe_auto_draw();
+
+            Ok(())
+        }
+
+        /// Plays with `amount` of the entry token, which the caller has to have
+        /// approved this contract to spend.
+        #[ink(message)]
+        pub fn play_with_token(&mut self, amount: Balance) -> Result<()> {
+            let token = match self.entry_token {
+                Some(token) => token,
+                None => return Err(Error::NoEntryToken),
+            };
+
+            let who = self.env().caller();
+            self.ensure_can_enter(who, amount)?;
+
+            self.transfer_token_from(token, who, amount)?;
+            self.enter(who, amount)?;
+            self.maybe_auto_draw();
+
+            Ok(())
+        }
+
+        /// Returns the transferred balance as a deposit, which a raffle with an
+        /// entry token only takes in that token.
+        fn native_deposit(&self) -> Result<Balance> {
+            if self.entry_token.is_some() {
+                return Err(Error::EntryTokenRequired);
+            }
+            Ok(self.env().transferred_balance())
+        }
+
+        /// Checks whether deposits can still be placed or raised.
+        fn ensure_deposits_open(&self) -> Result<()> {
+            if self.finished() {
+                return Err(Error::Finished);
+            }
+
+            if self.refunds_enabled {
+                return Err(Error::RefundsEnabled);
+            }
+
+            if self.freeze_entries_at_draw_time && self.draw_window_open() {
+                return Err(Error::EntriesFrozen);
+            }
+
+            if self.pool_fixed() {
+                return Err(Error::EntriesFrozen);
+            }
+
+            if self.entries_closed {
+                return Err(Error::EntriesClosed);
+            }
+
+            Ok(())
+        }
+
+        /// Returns whether the pool is fixed for the round, by `snapshot_on_first_draw`
+        /// or by the last draw while the raffle waits for `finalize`.
+        fn pool_fixed(&self) -> bool {
+            (self.snapshot_on_first_draw && !self.draw_records.is_empty()) || self.awaiting_finalize
+        }
+
+        /// Checks the preconditions of `play` that apply to every account.
+        fn ensure_entries_open(&self) -> Result<()> {
+            self.ensure_deposits_open()?;
+
+            if self.is_full() {
+                return Err(Error::RaffleFull);
+            }
+
+            if self.pot_remaining_to_cap() == Some(0) {
+                return Err(Error::PotCapReached);
+            }
+
+            Ok(())
+        }
+
+        /// Checks every precondition of `play` for `who` apart from the deposit amount.
+        fn ensure_can_play(&self, who: AccountId) -> Result<()> {
+            self.ensure_entries_open()?;
+
+            if self.self_excluded.contains_key(&who) {
+                return Err(Error::SelfExcluded);
+            }
+
+            if self.participants.get(&who).is_some() {
+                return Err(Error::HasPlayed);
+            }
+
+            if let Some(withdrawn_at) = self.last_withdraw_at.get(&who) {
+                if self.env().block_timestamp() < withdrawn_at.saturating_add(self.reentry_cooldown) {
+                    return Err(Error::ReentryCooldown);
+                }
+            }
+
+            self.ensure_action_allowed(who)
+        }
+
+        /// Checks whether `who` can enter with a deposit of `balance`, in either currency.
+        fn ensure_can_enter(&self, who: AccountId, balance: Balance) -> Result<()> {
+            self.ensure_can_play(who)?;
+
+            if balance < self.min_valid_deposit() || balance > self.max_valid_deposit() {
+                return Err(Error::InvalidDepositAmount);
+            }
+            self.ensure_increment(balance)?;
+
+            if matches!(self.pot_remaining_to_cap(), Some(remaining) if balance > remaining) {
+                return Err(Error::PotCapReached);
+            }
+
+            self.ensure_within_contribution_cap(who, balance)
+        }
+
+        /// Returns the smallest valid deposit under the current bounds.
+        fn min_valid_deposit(&self) -> Balance {
+            if self.bounds_inclusive {
+                MIN_DEPOSIT
+            } else {
+                MIN_DEPOSIT + 1
+            }
+        }
+
+        /// Returns the largest valid deposit under the current bounds.
+        fn max_valid_deposit(&self) -> Balance {
+            if self.bounds_inclusive {
+                MAX_DEPOSIT
+            } else {
+                MAX_DEPOSIT - 1
+            }
+        }
+
+        fn ensure_increment(&self, amount: Balance) -> Result<()> {
+            if self.deposit_increment > 1 && !amount.is_multiple_of(self.deposit_increment) {
+                return Err(Error::InvalidIncrement);
+            }
+            Ok(())
+        }
+
+        fn ensure_within_contribution_cap(&self, who: AccountId, amount: Balance) -> Result<()> {
+            let contributed = self.contributions.get(&who).copied().unwrap_or(0);
+            if matches!(self.max_contribution_per_account, Some(max) if contributed + amount > max) {
+                return Err(Error::ContributionCapExceeded);
+            }
+            Ok(())
+        }
+
+        fn add_contribution(&mut self, who: AccountId, amount: Balance) {
+            let contributed = self.contributions.get(&who).copied().unwrap_or(0);
+            self.contributions.insert(who, contributed + amount);
+        }
+
+        /// Integer square root, rounded down.
+        fn isqrt(n: Balance) -> Balance {
+            if n < 2 {
+                return n;
+            }
+            let mut x = n;
+            let mut y = n / 2 + n % 2;
+            while y < x {
+                x = y;
+                y = (x + n / x) / 2;
+            }
+            x
+        }
+
+        fn tickets_for(balance: Balance) -> u32 {
+            (balance / MIN_DEPOSIT) as u32
+        }
+
+        /// Records the entry of `who`, callers have to validate it first.
+        ///
+        /// Messages run one after another, so a second `play` from the same
+        /// account, even in the same block, already sees the first entry. The
+        /// check here still guards each entry path against overwriting a deposit.
+        fn enter(&mut self, who: AccountId, balance: Balance) -> Result<()> {
+            if self.participants.contains_key(&who) {
+                return Err(Error::DuplicateEntry);
+            }
+
+            self.credit_deposit(who, balance);
+            self.add_candidate(who);
+            self.entry_feed.push((who, self.env().block_timestamp()));
+
+            // a block producer can't know the entries that follow, which makes the
+            // final seed harder to steer than one built from block data alone.
+            let entropy = (self.entropy_accumulator, who, balance, self.env().block_timestamp());
+            self.entropy_accumulator = self.env().hash_encoded::<Blake2x256, _>(&entropy);
+
+            self.update_threshold();
+
+            let block = self.env().block_number();
+            self.block_entries = match self.block_entries {
+                (last, entries) if last == block => (block, entries + 1),
+                _ => (block, 1),
+            };
+
+            self.plays_count += 1;
+            self.total_played_volume += balance;
+            self.lifetime_plays_count += 1;
+            self.lifetime_played_volume += balance;
+
+            if self.emit_events {
+                self.env().emit_event(Played { who, balance });
+            }
+
+            Ok(())
+        }
+
+        /// Adds `amount` to the deposit of `who`, every entry path credits through here.
+        ///
+        /// The deposit, tickets and round contribution are updated together, and
+        /// draw weights are derived from them, so they can't drift apart.
+        fn credit_deposit(&mut self, who: AccountId, amount: Balance) {
+            let balance = self.participants.get(&who).copied().unwrap_or(0) + amount;
+            self.participants.insert(who, balance);
+            self.tickets.insert(who, Self::tickets_for(balance));
+            self.add_contribution(who, amount);
+            self.record_action(who);
+        }
+
+        /// Rejects an entry action from `who` within the action cooldown of its last one.
+        fn ensure_action_allowed(&self, who: AccountId) -> Result<()> {
+            if let Some(acted_at) = self.last_action_at.get(&who) {
+                if self.env().block_timestamp() < acted_at.saturating_add(self.action_cooldown) {
+                    return Err(Error::ActionTooSoon);
+                }
+            }
+            Ok(())
+        }
+
+        fn record_action(&mut self, who: AccountId) {
+            if self.action_cooldown > 0 {
+                self.last_action_at.insert(who, self.env().block_timestamp());
+            }
+        }
+
+        /// Starts the draw countdown the first time the minimum player count is reached.
+        fn update_threshold(&mut self) {
+            if !self.threshold_reached && self.participants.len() >= self.min_players {
+                let at = Self::env().block_timestamp();
+                self.threshold_reached = true;
+                self.draw_starts_at = at + DRAW_COUNTDOWN;
+                self.env().emit_event(ThresholdReached { at, players: self.participants.len() });
+            }
+        }
+
+        fn maybe_auto_draw(&mut self) {
+            if self.auto_draw && self.ensure_can_draw().is_ok() && !self.refund_if_below_soft_cap() {
+                // the entries stand even if the inline draw fails.
+                let _ = self.draw_winner();
+            }
+        }
+
+        /// Moves `amount` of `token` from `from`, which has to have approved it, to this contract.
+        fn transfer_token_from(&self, token: AccountId, from: AccountId, amount: Balance) -> Result<()> {
+            build_call::<Environment>()
+                .callee(token)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER_FROM_SELECTOR))
+                        .push_arg(from)
+                        .push_arg(self.env().account_id())
+                        .push_arg(amount)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<ReturnType<core::result::Result<(), Psp22Error>>>()
+                .fire()
+                .map_err(|_| Error::TokenTransferFailed)?
+                .map_err(|_| Error::TokenTransferFailed)
+        }
+
+        /// Sends `amount` of `token` held by this contract to `to`.
+        fn transfer_token(&self, token: AccountId, to: AccountId, amount: Balance) -> Result<()> {
+            build_call::<Environment>()
+                .callee(token)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER_SELECTOR))
+                        .push_arg(to)
+                        .push_arg(amount)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<ReturnType<core::result::Result<(), Psp22Error>>>()
+                .fire()
+                .map_err(|_| Error::TokenTransferFailed)?
+                .map_err(|_| Error::TokenTransferFailed)
+        }
+
+        /// Returns how much of `token` this contract holds, `None` if the call fails.
+        fn token_balance(&self, token: AccountId) -> Option<Balance> {
+            build_call::<Environment>()
+                .callee(token)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_BALANCE_OF_SELECTOR)).push_arg(self.env().account_id()),
+                )
+                .returns::<ReturnType<Balance>>()
+                .fire()
+                .ok()
+        }
+
+        /// Permanently stops the caller from playing.
+        ///
+        /// An existing entry stays in the raffle; the exclusion can't be undone.
+        #[ink(message)]
+        pub fn exclude_myself(&mut self) {
+            self.self_excluded.insert(self.env().caller(), ());
+        }
+
+        /// Plays the raffle and shows `name` on the supporters wall.
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn play_with_name(&mut self, name: Vec<u8>) -> Result<()> {
+            if name.len() > MAX_NAME_LEN {
+                return Err(Error::NameTooLong);
+            }
+
+            if core::str::from_utf8(&name).is_err() {
+                return Err(Error::InvalidName);
+            }
+
+            self.play()?;
+            self.names.insert(self.env().caller(), name);
+
+            Ok(())
+        }
+
+        /// Plays like `play` and tags the entry with `tag`, such as a team.
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn play_with_tag(&mut self, tag: u32) -> Result<()> {
+            let who = self.env().caller();
+            let balance = self.native_deposit()?;
+            self.ensure_can_enter(who, balance)?;
+
+            // tagged before entering, so an automatic draw already sees the tag.
+            self.tags.insert(who, tag);
+            self.enter(who, balance)?;
+            self.maybe_auto_draw();
+
+            Ok(())
+        }
+
+        /// Plays like `play` and votes for the charity at `charity_index`.
+        ///
+        /// The vote weighs as much as the entry's deposit, top-ups included.
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn play_with_vote(&mut self, charity_index: u32) -> Result<()> {
+            if charity_index >= self.charities.len() {
+                return Err(Error::InvalidCharity);
+            }
+
+            let who = self.env().caller();
+            let balance = self.native_deposit()?;
+            self.ensure_can_enter(who, balance)?;
+
+            // voted before entering, so an automatic draw already counts the vote.
+            self.charity_votes.insert(who, charity_index);
+            self.enter(who, balance)?;
+            self.maybe_auto_draw();
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn charities(&self) -> Vec<AccountId> {
+            self.charities.iter().copied().collect()
+        }
+
+        /// Returns the deposit-weighted votes of each candidate charity, in order.
+        #[ink(message)]
+        pub fn charity_tallies(&self) -> Vec<Balance> {
+            let mut tallies: Vec<Balance> = self.charities.iter().map(|_| 0).collect();
+            for (who, index) in self.charity_votes.iter() {
+                tallies[*index as usize] += self.participants.get(who).copied().unwrap_or(0);
+            }
+            tallies
+        }
+
+        /// Returns the charity with the most deposit-weighted votes, `None` while
+        /// no entry has voted. Ties go to the charity listed first.
+        #[ink(message)]
+        pub fn leading_charity(&self) -> Option<AccountId> {
+            let tallies = self.charity_tallies();
+            let mut leading: Option<(usize, Balance)> = None;
+            for (index, votes) in tallies.into_iter().enumerate() {
+                let ahead = match leading {
+                    Some((_, most)) => votes > most,
+                    None => true,
+                };
+                if votes > 0 && ahead {
+                    leading = Some((index, votes));
+                }
+            }
+            leading.and_then(|(index, _)| self.charities.get(index as u32).copied())
+        }
+
+        /// Returns the tag of `who`, if one was given.
+        #[ink(message)]
+        pub fn tag_of(&self, who: AccountId) -> Option<u32> {
+            self.tags.get(&who).copied()
+        }
+
+        /// Sets whether at most one account per tag can win, only before the first draw.
+        ///
+        /// Untagged accounts are not limited. Once every remaining candidate
+        /// shares a tag with a winner, the raffle finishes with fewer winners.
+        #[ink(message)]
+        pub fn set_one_winner_per_tag(&mut self, one_winner_per_tag: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.one_winner_per_tag = one_winner_per_tag;
+            Ok(())
+        }
+
+        /// Sets the smallest deposit that can win, only before the first draw.
+        ///
+        /// Smaller deposits still enter and go to the beneficiary, they are just
+        /// never drawn. Once no remaining deposit is large enough, the raffle
+        /// finishes with fewer winners, or refunds every deposit if none was
+        /// large enough at the first draw.
+        #[ink(message)]
+        pub fn set_min_winning_deposit(&mut self, min_winning_deposit: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.min_winning_deposit = min_winning_deposit;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn min_winning_deposit(&self) -> Balance {
+            self.min_winning_deposit
+        }
+
+        /// Returns the display name of `who`, if one was given.
+        #[ink(message)]
+        pub fn name_of(&self, who: AccountId) -> Option<Vec<u8>> {
+            self.names.get(&who).cloned()
+        }
+
+        /// Adds the transferred balance to the caller's deposit.
+        ///
+        /// The combined deposit must not exceed the maximum deposit.
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn increase_deposit(&mut self) -> Result<()> {
+            let increase = self.native_deposit()?;
+            self.ensure_deposits_open()?;
+
+            let who = self.env().caller();
+            let deposit = match self.participants.get(&who) {
+                Some(deposit) => *deposit,
+                None => return Err(Error::HasNotPlayed),
+            };
+            self.ensure_action_allowed(who)?;
+
+            let balance = deposit + increase;
+            if increase == 0 || balance > self.max_valid_deposit() {
+                return Err(Error::InvalidDepositAmount);
+            }
+            self.ensure_increment(increase)?;
+
+            if matches!(self.pot_remaining_to_cap(), Some(remaining) if increase > remaining) {
+                return Err(Error::PotCapReached);
+            }
+            self.ensure_within_contribution_cap(who, increase)?;
+
+            self.credit_deposit(who, increase);
+
+            if self.emit_events {
+                self.env().emit_event(DepositIncreased { who, balance });
+            }
+
+            Ok(())
+        }
+
+        /// Withdraws the caller's entry and refunds the deposit.
+        ///
+        /// Entries can be withdrawn until the first winner is drawn.
+        #[ink(message)]
+        pub fn withdraw_entry(&mut self) -> Result<()> {
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.ensure_nothing_released()?;
+
+            let who = self.env().caller();
+            let balance = match self.participants.get(&who) {
+                Some(deposit) => *deposit,
+                None => return Err(Error::HasNotPlayed),
+            };
+            self.ensure_action_allowed(who)?;
+
+            self.refund_deposit(who, balance)?;
+
+            self.participants.take(&who);
+            self.tickets.take(&who);
+            self.names.take(&who);
+            self.tags.take(&who);
+            self.charity_votes.take(&who);
+            self.remove_candidate(who);
+            self.last_withdraw_at.insert(who, self.env().block_timestamp());
+            self.record_action(who);
+            self.add_refunded(who, balance);
+
+            if self.emit_events {
+                self.env().emit_event(Withdrawn { who, balance });
+            }
+
+            Ok(())
+        }
+
+        /// Pays `balance` of a deposit back to `who`, in the entry token if there is one.
+        fn refund_deposit(&self, who: AccountId, balance: Balance) -> Result<()> {
+            match self.entry_token {
+                Some(token) => self.transfer_token(token, who, balance),
+                None => self.env().transfer(who, balance).map_err(|_| Error::TransferFailed),
+            }
+        }
+
+        /// Sets how long an account has to wait after withdrawing before it can play again.
+        #[ink(message)]
+        pub fn set_reentry_cooldown(&mut self, cooldown: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            self.reentry_cooldown = cooldown;
+            Ok(())
+        }
+
+        /// Sets how long an account has to wait between entering, increasing and
+        /// withdrawing its deposit, zero disabling the limit.
+        #[ink(message)]
+        pub fn set_action_cooldown(&mut self, cooldown: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            self.action_cooldown = cooldown;
+            Ok(())
+        }
+
+        /// Notifies the registry, if any, that the raffle finished.
+        ///
+        /// Failures are ignored so a broken registry can't block the payout.
+        fn report_finished(&self, total_paid: Balance) {
+            if let Some(registry) = self.registry {
+                let winners: Vec<AccountId> = self.winners.iter().copied().collect();
+                let _ = build_call::<Environment>()
+                    .callee(registry)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(REPORT_FINISHED_SELECTOR))
+                            .push_arg(winners)
+                            .push_arg(total_paid),
+                    )
+                    .returns::<()>()
+                    .fire();
+            }
+        }
+
+        /// Tells the notifier about a winner, a failing notifier doesn't stop the draw.
+        fn notify_winner(&self, who: AccountId, index: u32) {
+            if let Some(notifier) = self.notifier {
+                let _ = build_call::<Environment>()
+                    .callee(notifier)
+                    .exec_input(
+                        ExecutionInput::new(Selector::new(NOTIFY_WINNER_SELECTOR))
+                            .push_arg(who)
+                            .push_arg(index),
+                    )
+                    .returns::<()>()
+                    .fire();
+            }
+        }
+
+        /// Sets the contract told about each winner, `None` disables it.
+        #[ink(message)]
+        pub fn set_notifier(&mut self, notifier: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.notifier = notifier;
+            Ok(())
+        }
+
+        /// Sets the registry notified when the raffle finishes, `None` disables it.
+        #[ink(message)]
+        pub fn set_registry(&mut self, registry: Option<AccountId>) -> Result<()> {
+            self.ensure_owner()?;
+            self.registry = registry;
+            Ok(())
+        }
+
+        /// Sets whether non-essential events are emitted.
+        ///
+        /// `ThresholdReached`, `Draw` and `Finished` are essential and always emitted;
+        /// `Played`, `DepositIncreased`, `Withdrawn` and `Donated` are skipped when disabled.
+        #[ink(message)]
+        pub fn set_emit_events(&mut self, emit_events: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.emit_events = emit_events;
+            Ok(())
+        }
+
+        /// Sets whether the participants with the largest deposit are excluded from winning.
+        ///
+        /// All participants tied for the largest deposit are excluded, so it can't
+        /// be enabled while every participant is tied for it. Once no candidate
+        /// left can win, the raffle finishes with fewer winners.
+        #[ink(message)]
+        pub fn set_exclude_top_donor_from_winning(&mut self, exclude: bool) -> Result<()> {
+            self.ensure_owner()?;
+            let top = self.participants.values().max().copied();
+            if exclude && top.is_some() && self.participants.values().all(|deposit| Some(*deposit) == top) {
+                return Err(Error::NoEligibleCandidates);
+            }
+            self.exclude_top_donor_from_winning = exclude;
+            Ok(())
+        }
+
+        /// Sets whether removing a candidate keeps the others in entry order.
+        ///
+        /// Swap removal writes a single slot. Stable removal shifts every later
+        /// candidate down by one, so its gas cost grows with the candidate count.
+        #[ink(message)]
+        pub fn set_stable_removal(&mut self, stable_removal: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.stable_removal = stable_removal;
+            Ok(())
+        }
+
+        /// Sets how many draws one block may hold.
+        ///
+        /// Spreading draws over blocks keeps a single block producer from
+        /// influencing all of them.
+        #[ink(message)]
+        pub fn set_max_draws_per_block(&mut self, max: u32) -> Result<()> {
+            self.ensure_owner()?;
+            if max == 0 {
+                return Err(Error::InvalidDrawRateLimit);
+            }
+            self.max_draws_per_block = max;
+            Ok(())
+        }
+
+        /// Sets the time that has to pass between two draws, at most `MAX_DRAW_GAP`.
+        #[ink(message)]
+        pub fn set_draw_gap(&mut self, gap: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            if self.finished() {
+                return Err(Error::Finished);
+            }
+            if gap > MAX_DRAW_GAP {
+                return Err(Error::InvalidDrawGap);
+            }
+
+            self.draw_gap = gap;
+            self.env().emit_event(DrawGapChanged { gap });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn draw_gap(&self) -> Timestamp {
+            self.draw_gap
+        }
+
+        /// Sets whether the candidates are shuffled before the first draw of a round.
+        ///
+        /// The shuffle breaks any link between entry order and the positions swap
+        /// removal leaves candidates in.
+        #[ink(message)]
+        pub fn set_shuffle_before_draw(&mut self, shuffle: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.shuffle_before_draw = shuffle;
+            Ok(())
+        }
+
+        /// Sets what happens when the beneficiary is drawn, only before the first draw.
+        #[ink(message)]
+        pub fn set_winner_beneficiary_policy(&mut self, policy: WinnerBeneficiaryPolicy) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.winner_beneficiary_policy = policy;
+            Ok(())
+        }
+
+        /// Sets how candidates are weighted in a draw, only before the first draw.
+        ///
+        /// Equal odds index the pool directly. Weighted draws walk the pool
+        /// summing weights, so their gas cost grows with the candidate count.
+        #[ink(message)]
+        pub fn set_weighting(&mut self, weighting: Weighting) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.weighting = weighting;
+            Ok(())
+        }
+
+        /// Pauses drawing, entries are still accepted.
+        #[ink(message)]
+        pub fn pause_draw(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.draw_paused = true;
+            Ok(())
+        }
+
+        /// Resumes drawing.
+        #[ink(message)]
+        pub fn resume_draw(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.draw_paused = false;
+            Ok(())
+        }
+
+        /// Closes entries once deposits reach `cap`, `None` removes the cap.
+        ///
+        /// Entries and top-ups that would push the pot past the cap are rejected.
+        #[ink(message)]
+        pub fn set_pot_cap(&mut self, cap: Option<Balance>) -> Result<()> {
+            self.ensure_owner()?;
+            self.pot_cap = cap;
+            Ok(())
+        }
+
+        /// Returns how much more can be deposited before the pot cap, `None` if uncapped.
+        #[ink(message)]
+        pub fn pot_remaining_to_cap(&self) -> Option<Balance> {
+            self.pot_cap.map(|cap| cap.saturating_sub(self.total_pot()))
+        }
+
+        /// Changes the players required before the draw countdown starts.
+        ///
+        /// Only allowed before the first draw. The minimum must leave at least one
+        /// loser, and may be at most `MIN_PLAYERS_SLACK` above the current players.
+        /// Lowering it to the current player count starts the countdown.
+        #[ink(message)]
+        pub fn set_min_players(&mut self, min_players: u32) -> Result<()> {
+            self.ensure_owner()?;
+
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+
+            if min_players < WINNER_COUNT + 1 || min_players > self.participants.len() + MIN_PLAYERS_SLACK {
+                return Err(Error::InvalidMinPlayers);
+            }
+
+            self.min_players = min_players;
+            self.env().emit_event(MinPlayersChanged { min_players });
+            self.update_threshold();
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn min_players(&self) -> u32 {
+            self.min_players
+        }
+
+        /// Caps the number of participants, `None` removes the cap.
+        #[ink(message)]
+        pub fn set_max_participants(&mut self, max: Option<u32>) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_participants = max;
+            Ok(())
+        }
+
+        /// Returns how many more participants can play, `None` if uncapped.
+        #[ink(message)]
+        pub fn slots_remaining(&self) -> Option<u32> {
+            self.max_participants.map(|max| max.saturating_sub(self.participants.len()))
+        }
+
+        /// Returns whether a participant cap is set and has been reached.
+        #[ink(message)]
+        pub fn is_full(&self) -> bool {
+            self.slots_remaining() == Some(0)
+        }
+
+        /// Donates to the beneficiary without entering the raffle.
+        ///
+        /// Donations are forwarded together with the deposits when the raffle finishes.
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn donate(&mut self) -> Result<()> {
+            if self.finished() {
+                return Err(Error::Finished);
+            }
+
+            let balance = self.env().transferred_balance();
+            if balance == 0 {
+                return Err(Error::InvalidDonationAmount);
+            }
+
+            self.donations += balance;
+
+            let who = self.env().caller();
+            if self.emit_events {
+                self.env().emit_event(Donated { who, balance });
+            }
+
+            Ok(())
+        }
+
+        /// Returns whether a new account could enter right now with a minimum deposit.
+        #[ink(message)]
+        pub fn entries_open(&self) -> bool {
+            self.ensure_entries_open().is_ok()
+                && !matches!(self.pot_remaining_to_cap(), Some(remaining) if self.min_valid_deposit() > remaining)
+        }
+
+        /// Checks whether the caller could play right now, given a valid deposit.
+        #[ink(message)]
+        pub fn can_i_play(&self) -> Result<()> {
+            self.ensure_can_play(self.env().caller())
+        }
+
+        /// Returns the first precondition keeping `who` from playing, `None` if
+        /// `who` could play with a valid deposit.
+        #[ink(message)]
+        pub fn play_blocker(&self, who: AccountId) -> Option<Error> {
+            self.ensure_can_play(who).err()
+        }
+
+        /// Returns the smallest transfer that would make `play` succeed for the
+        /// caller right now, zero if no transfer would.
+        #[ink(message)]
+        pub fn required_entry_amount(&self) -> Balance {
+            let min = self.min_valid_deposit();
+            let amount = match self.deposit_increment {
+                0 | 1 => min,
+                increment => min.div_ceil(increment) * increment,
+            };
+
+            if self.ensure_can_enter(self.env().caller(), amount).is_err() {
+                return 0;
+            }
+            amount
+        }
+
+        /// Returns the earliest time the next draw is allowed.
+        fn next_draw_at(&self) -> Timestamp {
+            match self.draw_records.len() {
+                0 => self.draw_starts_at,
+                len => {
+                    let last = self.draw_records.get(len - 1).map(|r| r.timestamp).unwrap_or(0);
+                    self.draw_starts_at.max(last + self.draw_gap)
+                }
+            }
+        }
+
+        fn ensure_can_draw(&self) -> Result<()> {
+            if self.finished() {
+                return Err(Error::Finished);
+            }
+
+            if self.awaiting_finalize {
+                return Err(Error::AwaitingFinalize);
+            }
+
+            if self.refunds_enabled {
+                return Err(Error::RefundsEnabled);
+            }
+
+            if self.draw_paused {
+                return Err(Error::DrawPaused);
+            }
+
+            if !self.draw_window_open() {
+                return Err(Error::DrawNotStarted);
+            }
+
+            if self.env().block_timestamp() < self.next_draw_at() {
+                return Err(Error::DrawTooSoon);
+            }
+
+            if self.manual_close && !self.entries_closed {
+                return Err(Error::EntriesOpen);
+            }
+
+            let (block, draws) = self.block_draws;
+            if block == self.env().block_number() && draws >= self.max_draws_per_block {
+                return Err(Error::DrawRateLimited);
+            }
+
+            if self.participants.len() < self.min_players {
+                return Err(Error::NotEnoughPlayer);
+            }
+
+            if self.candidates.is_empty() {
+                return Err(Error::NoCandidates);
+            }
+
+            if self.seed_commitment.is_some() && self.revealed_seed.is_none() {
+                return Err(Error::SeedNotRevealed);
+            }
+
+            Ok(())
+        }
+
+        /// Returns the Blake2x256 hash of `seed`, the commitment `reveal_seed` checks against.
+        #[ink(message)]
+        pub fn hash_seed(&self, seed: [u8; 32]) -> [u8; 32] {
+            self.env().hash_bytes::<Blake2x256>(&seed)
+        }
+
+        /// Sets how long after the draw window opens a committed seed may stay
+        /// unrevealed before anyone can `draw_fallback`, zero disabling it.
+        ///
+        /// It can't be changed once a seed is committed, so the owner can't stall
+        /// a draw by extending it.
+        #[ink(message)]
+        pub fn set_reveal_timeout(&mut self, timeout: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            if self.seed_commitment.is_some() {
+                return Err(Error::SeedAlreadyCommitted);
+            }
+            self.reveal_timeout = timeout;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn reveal_timeout(&self) -> Timestamp {
+            self.reveal_timeout
+        }
+
+        /// Returns whether the committed seed is still unrevealed `reveal_timeout`
+        /// after the draw window opened.
+        fn reveal_timed_out(&self) -> bool {
+            self.seed_commitment.is_some()
+                && self.revealed_seed.is_none()
+                && self.reveal_timeout > 0
+                && self.draw_window_open()
+                && self.env().block_timestamp() >= self.draw_starts_at.saturating_add(self.reveal_timeout)
+        }
+
+        /// Draws a winner without the committed seed once it has stayed
+        /// unrevealed past the reveal timeout, callable by anyone.
+        ///
+        /// The draw uses the chain's `random()` alone, skipping any oracle, and is
+        /// recorded as using the fallback, so an unresponsive owner can't lock the
+        /// deposits. An all-zero output fails with `RandomnessUnavailable`. Every
+        /// later draw of the round can go through here too.
+        #[ink(message)]
+        pub fn draw_fallback(&mut self) -> Result<()> {
+            if !self.reveal_timed_out() {
+                return Err(Error::RevealNotTimedOut);
+            }
+
+            match self.ensure_can_draw() {
+                Ok(()) | Err(Error::SeedNotRevealed) => {}
+                Err(error) => return Err(error),
+            }
+
+            if self.refund_if_below_soft_cap() {
+                return Ok(());
+            }
+
+            if self.drawing {
+                return Err(Error::DrawInProgress);
+            }
+
+            if self.refund_if_no_one_can_win() {
+                return Ok(());
+            }
+
+            if self.eligible_candidates().is_empty() {
+                return Err(Error::NoEligibleCandidates);
+            }
+
+            let randomness = self.env().random(&self.draw_seed()[..]);
+            if randomness.as_ref().iter().all(|b| *b == 0) {
+                return Err(Error::RandomnessUnavailable);
+            }
+            self.used_fallback = true;
+            self.draw_with(randomness, true)
+        }
+
+        /// Commits to a seed that will be mixed into every draw.
+        ///
+        /// Pass `hash_seed(seed)`. Once committed, draws wait until the seed is revealed.
+        #[ink(message)]
+        pub fn commit_seed(&mut self, commitment: [u8; 32]) -> Result<()> {
+            self.ensure_owner()?;
+
+            if self.seed_commitment.is_some() {
+                return Err(Error::SeedAlreadyCommitted);
+            }
+
+            if self.draw_window_open() {
+                return Err(Error::CommitClosed);
+            }
+
+            self.seed_commitment = Some(commitment);
+            Ok(())
+        }
+
+        /// Reveals the committed seed.
+        #[ink(message)]
+        pub fn reveal_seed(&mut self, seed: [u8; 32]) -> Result<()> {
+            let commitment = self.seed_commitment.ok_or(Error::NoSeedCommitted)?;
+            if self.hash_seed(seed) != commitment {
+                return Err(Error::InvalidSeed);
+            }
+
+            self.revealed_seed = Some(seed);
+            Ok(())
+        }
+
+        /// Returns where the round's seed commitment is in its commit-reveal lifecycle.
+        #[ink(message)]
+        pub fn rng_commitment_status(&self) -> RngStatus {
+            match (self.seed_commitment, self.revealed_seed) {
+                (_, Some(seed)) => RngStatus::Revealed { seed },
+                (Some(hash), None) => RngStatus::Committed { hash },
+                (None, None) => RngStatus::NotCommitted,
+            }
+        }
+
+        /// Returns the commitment `commit_entry` expects from `who` for `nonce`.
+        #[ink(message)]
+        pub fn hash_entry(&self, who: AccountId, nonce: [u8; 32]) -> [u8; 32] {
+            self.env().hash_encoded::<Blake2x256, _>(&(who, nonce))
+        }
+
+        /// Stakes a hidden entry, `reveal_entry` turns it into a regular one.
+        ///
+        /// Pass `hash_entry(caller, nonce)`. The stake must be a valid deposit,
+        /// and the entry joins the raffle only once revealed before the first draw.
+        #[ink(message)]
+        #[ink(payable)]
+        pub fn commit_entry(&mut self, commitment: [u8; 32]) -> Result<()> {
+            let who = self.env().caller();
+            let stake = self.native_deposit()?;
+            self.ensure_can_enter(who, stake)?;
+            if self.entry_commitments.contains_key(&who) {
+                return Err(Error::EntryAlreadyCommitted);
+            }
+
+            self.entry_commitments.insert(who, (commitment, stake, self.round));
+            Ok(())
+        }
+
+        /// Reveals the caller's hidden entry and enters the stake as their deposit.
+        #[ink(message)]
+        pub fn reveal_entry(&mut self, nonce: [u8; 32]) -> Result<()> {
+            let who = self.env().caller();
+            let (commitment, stake, round) = match self.entry_commitments.get(&who) {
+                Some(entry) => *entry,
+                None => return Err(Error::NoEntryCommitted),
+            };
+
+            if round != self.round || !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            if self.hash_entry(who, nonce) != commitment {
+                return Err(Error::InvalidEntryReveal);
+            }
+            self.ensure_can_enter(who, stake)?;
+
+            self.entry_commitments.take(&who);
+            self.enter(who, stake)?;
+            self.maybe_auto_draw();
+
+            Ok(())
+        }
+
+        /// Withdraws the caller's unrevealed hidden entry and refunds its stake.
+        ///
+        /// Only possible while the entry could still be revealed.
+        #[ink(message)]
+        pub fn cancel_commitment(&mut self) -> Result<()> {
+            let who = self.env().caller();
+            let (_, stake, round) = match self.entry_commitments.get(&who) {
+                Some(entry) => *entry,
+                None => return Err(Error::NoEntryCommitted),
+            };
+
+            let closed = !self.draw_records.is_empty() || self.finished() || self.refunds_enabled;
+            if round != self.round || closed {
+                return Err(Error::DrawStarted);
+            }
+
+            if self.env().transfer(who, stake).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.entry_commitments.take(&who);
+            self.add_refunded(who, stake);
+            Ok(())
+        }
+
+        /// Settles the stake of a hidden entry of `who` that was never revealed.
+        ///
+        /// Once the round's first draw is made, or the round has ended, the stake
+        /// goes back to `who`, or to the beneficiary with
+        /// `forfeit_unrevealed_entries`.
+        #[ink(message)]
+        pub fn settle_entry_commitment(&mut self, who: AccountId) -> Result<()> {
+            let (_, stake, round) = match self.entry_commitments.get(&who) {
+                Some(entry) => *entry,
+                None => return Err(Error::NoEntryCommitted),
+            };
+
+            let closed = !self.draw_records.is_empty() || self.finished() || self.refunds_enabled;
+            if round == self.round && !closed {
+                return Err(Error::RevealOpen);
+            }
+
+            if self.forfeit_unrevealed_entries {
+                self.pending_withdrawal += stake;
+            } else if self.env().transfer(who, stake).is_err() {
+                return Err(Error::TransferFailed);
+            } else {
+                self.add_refunded(who, stake);
+            }
+
+            self.entry_commitments.take(&who);
+            Ok(())
+        }
+
+        /// Sets whether unrevealed hidden entries are forfeited to the beneficiary.
+        #[ink(message)]
+        pub fn set_forfeit_unrevealed_entries(&mut self, forfeit: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.forfeit_unrevealed_entries = forfeit;
+            Ok(())
+        }
+
+        /// Returns the indices of the candidates that may win the next draw.
+        ///
+        /// With `exclude_top_donor_from_winning`, every participant whose deposit
+        /// equals the largest deposit is left out; their deposit still goes to
+        /// the beneficiary. Under `WinnerBeneficiaryPolicy::Reroll` the
+        /// beneficiary and candidate charities are left out too, and with
+        /// `one_winner_per_tag` so is every
+        /// account sharing a tag with a winner. Deposits below `min_winning_deposit`
+        /// are left out as well.
+        fn eligible_candidates(&self) -> Vec<u32> {
+            let top_deposit = if self.exclude_top_donor_from_winning {
+                self.participants.values().max().copied()
+            } else {
+                None
+            };
+
+            let reroll_beneficiary = self.winner_beneficiary_policy == WinnerBeneficiaryPolicy::Reroll;
+            let won_tags: Vec<u32> = if self.one_winner_per_tag {
+                self.winners.iter().filter_map(|w| self.tags.get(w).copied()).collect()
+            } else {
+                Vec::new()
+            };
+
+            (0..self.candidates.len())
+                .filter(|i| match top_deposit {
+                    Some(top) => self.participants.get(&self.candidates[*i]) != Some(&top),
+                    None => true,
+                })
+                .filter(|i| !reroll_beneficiary || !self.is_recipient(self.candidates[*i]))
+                .filter(|i| {
+                    self.participants.get(&self.candidates[*i]).copied().unwrap_or(0) >= self.min_winning_deposit
+                })
+                .filter(|i| match self.tags.get(&self.candidates[*i]) {
+                    Some(tag) => !won_tags.contains(tag),
+                    None => true,
+                })
+                .collect()
+        }
+
+        /// Ends the drawing, finishing the raffle now or leaving it to `finalize`.
+        fn complete_draws(&mut self) {
+            if self.explicit_finalize {
+                self.awaiting_finalize = true;
+            } else {
+                self.finish();
+            }
+        }
+
+        /// Settles the raffle once every winner is drawn: pays out the pot, mints
+        /// the prizes and marks the raffle finished.
+        ///
+        /// Only needed when `explicit_finalize` is set, otherwise the last draw
+        /// settles the raffle itself.
+        #[ink(message)]
+        pub fn finalize(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            if self.refunds_enabled {
+                return Err(Error::RefundsEnabled);
+            }
+            if !self.awaiting_finalize {
+                return Err(Error::NotAwaitingFinalize);
+            }
+
+            self.awaiting_finalize = false;
+            self.finish();
+            Ok(())
+        }
+
+        /// Sets whether the owner settles the raffle with `finalize` rather than
+        /// the last draw doing it, only before the first draw.
+        #[ink(message)]
+        pub fn set_explicit_finalize(&mut self, explicit: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.explicit_finalize = explicit;
+            Ok(())
+        }
+
+        /// Pays out the pot and marks the raffle finished.
+        ///
+        /// With candidate charities, the pot goes to the leading charity as this
+        /// round's recipient, the beneficiary is left unchanged.
+        fn finish(&mut self) {
+            // the recipient is read before `finished_at` is set, since a finished
+            // raffle without a recorded recipient pays the beneficiary.
+            let recipient = self.payout_recipient();
+            if recipient != self.beneficiary {
+                self.round_recipient = Some(recipient);
+            }
+            self.finished_at = Some(self.env().block_timestamp());
+            let winners = self.winners.iter().copied().collect();
+            self.round_winners.insert(self.round, winners);
+
+            let deposits = self.total_pot();
+            let (balance, rollover) = self.payout_split();
+            self.rollover_balance += rollover;
+
+            // an empty pot is not transferred, `Finished` still reports the zero payout.
+            if balance > 0 {
+                let payable = self.payable(balance);
+                // a failed or partial push leaves the rest for the recipient to withdraw.
+                let pushed = self.push_payout && payable > 0 && self.env().transfer(recipient, payable).is_ok();
+                let owed = if pushed { balance - payable } else { balance };
+                if pushed {
+                    self.lifetime_donated += payable;
+                }
+                if self.round_recipient.is_some() {
+                    self.recipient_pending += owed;
+                } else {
+                    self.pending_withdrawal += owed;
+                }
+            }
+
+            self.env().emit_event(Finished { beneficiary: recipient, balance });
+            self.rounds_completed += 1;
+
+            if let Some(token) = self.entry_token.filter(|_| deposits > 0) {
+                if !self.push_payout || self.transfer_token(token, self.beneficiary, deposits).is_err() {
+                    self.pending_token_withdrawal += deposits;
+                } else {
+                    self.env().emit_event(TokenPaidOut { token, beneficiary: self.beneficiary, balance: deposits });
+                }
+            }
+            self.mint_prizes();
+
+            self.report_finished(balance);
+        }
+
+        /// Splits the pot and donations into the payout and the rollover.
+        ///
+        /// Milestone releases already left the contract, so they come out of the
+        /// payout, and the rollover never takes more than what remains. Deposits
+        /// in the entry token are paid out apart from the native donations.
+        fn payout_split(&self) -> (Balance, Balance) {
+            let pot = match self.entry_token {
+                Some(_) => self.donations,
+                None => self.total_pot() + self.donations,
+            };
+            let remaining = pot.saturating_sub(self.milestone_released_balance);
+            let rollover = pot * self.rollover_bps as Balance / BPS_DENOMINATOR as Balance;
+            let rollover = core::cmp::min(rollover, remaining);
+            (remaining - rollover, rollover)
+        }
+
+        /// Maps `randomness` uniformly to an index below `len`.
+        ///
+        /// The randomness is read as eight 32-bit values, and values in the tail
+        /// above the largest multiple of `len` are rejected so every index is
+        /// equally likely. A value is rejected with probability below
+        /// `len / 2^32`, so the first one is almost always used; if all eight are
+        /// rejected the randomness is rehashed and read again.
+        fn pick(&self, randomness: &Hash, len: usize) -> usize {
+            let len = len as u64;
+            let zone = (1u64 << 32) / len * len;
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(randomness.as_ref());
+            loop {
+                for chunk in bytes.chunks(4) {
+                    let value = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as u64;
+                    if value < zone {
+                        return (value % len) as usize;
+                    }
+                }
+                bytes = self.env().hash_bytes::<Blake2x256>(&bytes);
+            }
+        }
+
+        /// Picks an index with probability proportional to its weight.
+        ///
+        /// The weights can sum past 32 bits, so the randomness is read as four
+        /// 64-bit values with the same rejection as `pick`.
+        fn pick_weighted(&self, randomness: &Hash, weights: &[u32]) -> usize {
+            let total: u64 = weights.iter().map(|w| *w as u64).sum();
+            let zone = (1u128 << 64) / total as u128 * total as u128;
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(randomness.as_ref());
+            let mut target = 'draw: loop {
+                for chunk in bytes.chunks(8) {
+                    let mut value = [0u8; 8];
+                    value.copy_from_slice(chunk);
+                    let value = u64::from_le_bytes(value);
+                    if (value as u128) < zone {
+                        break 'draw value % total;
+                    }
+                }
+                bytes = self.env().hash_bytes::<Blake2x256>(&bytes);
+            };
+
+            for (index, weight) in weights.iter().enumerate() {
+                if target < *weight as u64 {
+                    return index;
+                }
+                target -= *weight as u64;
+            }
+            weights.len() - 1
+        }
+
+        /// Returns the draw weight of `who` under the current weighting.
+        fn weight_of(&self, who: AccountId) -> u32 {
+            match self.weighting {
+                Weighting::Equal => 1,
+                Weighting::Tickets => self.tickets_of(who),
+                Weighting::SqrtDeposit => {
+                    Self::isqrt(self.participants.get(&who).copied().unwrap_or(0)) as u32
+                }
+            }
+        }
+
+        /// Returns the draw weight of each eligible candidate, empty when odds are equal.
+        fn candidate_weights(&self, eligible: &[u32]) -> Vec<u32> {
+            if self.weighting == Weighting::Equal {
+                return Vec::new();
+            }
+            eligible.iter().map(|i| self.weight_of(self.candidates[*i])).collect()
+        }
+
+        /// Shuffles the candidates with Fisher-Yates, seeded from the draw randomness.
+        ///
+        /// The shuffle swaps every candidate once and hashes a fresh seed for each
+        /// swap, so its gas cost grows with the candidate count.
+        fn shuffle_candidates(&mut self, randomness: &Hash) {
+            let mut seed = Hash::from(self.env().hash_encoded::<Blake2x256, _>(&(randomness, b"shuffle")));
+            for i in (1..self.candidates.len()).rev() {
+                let j = self.pick(&seed, i as usize + 1) as u32;
+                self.candidates.swap(i, j);
+                seed = Hash::from(self.env().hash_bytes::<Blake2x256>(seed.as_ref()));
+            }
+            self.shuffled = true;
+        }
+
+        /// Recomputes the pick of draw `index` from its record. Draws whose pick was
+        /// forfeited have a record too, so indices can run ahead of `winners`.
+        ///
+        /// Records keep the whole eligible pool, so the storage they take grows
+        /// with the number of candidates.
+        #[ink(message)]
+        pub fn verify_draw(&self, index: u32) -> bool {
+            match self.draw_records.get(index) {
+                Some(record) if !record.pool.is_empty() => {
+                    let index = if record.weights.is_empty() {
+                        self.pick(&record.randomness, record.pool.len())
+                    } else {
+                        self.pick_weighted(&record.randomness, &record.weights)
+                    };
+                    record.pool[index] == record.drawn
+                }
+                _ => false,
+            }
+        }
+
+        /// Returns the randomness the most recent draw of the round picked with.
+        #[ink(message)]
+        pub fn last_draw_randomness(&self) -> Option<Hash> {
+            match self.draw_records.len() {
+                0 => None,
+                len => self.draw_records.get(len - 1).map(|r| r.randomness),
+            }
+        }
+
+        /// Returns the pot and donations, less milestone releases, at each draw of
+        /// the round, in draw order.
+        ///
+        /// The last value is the payout of a finished round, before any rollover.
+        #[ink(message)]
+        pub fn pot_history(&self) -> Vec<Balance> {
+            self.pot_at_draw.iter().copied().collect()
+        }
+
+        /// Returns whether draw `index` used the deterministic fallback seed.
+        #[ink(message)]
+        pub fn draw_used_fallback(&self, index: u32) -> bool {
+            self.draw_records.get(index).map(|r| r.used_fallback).unwrap_or(false)
+        }
+
+        #[ink(message)]
+        pub fn draw_record(&self, index: u32) -> Option<DrawRecord> {
+            self.draw_records.get(index).cloned()
+        }
+
+        /// Draws one winner, callers have to check `ensure_can_draw` first.
+        fn draw_winner(&mut self) -> Result<()> {
+            if self.drawing {
+                return Err(Error::DrawInProgress);
+            }
+
+            if self.refund_if_no_one_can_win() {
+                return Ok(());
+            }
+
+            let eligible = self.eligible_candidates();
+            if eligible.is_empty() {
+                return Err(Error::NoEligibleCandidates);
+            }
+
+            let (randomness, used_fallback) = self.randomness()?;
+            self.draw_with(randomness, used_fallback)
+        }
+
+        /// Enables refunds in place of the first draw when no candidate can win,
+        /// such as when every deposit is below `min_winning_deposit`, returning
+        /// whether it did. After the first draw the raffle finishes early instead.
+        fn refund_if_no_one_can_win(&mut self) -> bool {
+            if !self.draw_records.is_empty() || !self.eligible_candidates().is_empty() {
+                return false;
+            }
+            self.enable_refunds();
+            true
+        }
+
+        /// Draws one winner with `randomness`, the part of a draw after fetching it.
+        fn draw_with(&mut self, randomness: Hash, used_fallback: bool) -> Result<()> {
+            // the shuffle moves the candidates, so their eligible positions are taken after it.
+            if self.shuffle_before_draw && !self.shuffled {
+                self.shuffle_candidates(&randomness);
+            }
+
+            let eligible = self.eligible_candidates();
+
+            if eligible.is_empty() {
+                return Err(Error::NoEligibleCandidates);
+            }
+
+            let weights = self.candidate_weights(&eligible);
+            let pick = if weights.is_empty() {
+                self.pick(&randomness, eligible.len())
+            } else {
+                self.pick_weighted(&randomness, &weights)
+            };
+            let winner_index = eligible[pick];
+            let winner = self.candidates[winner_index];
+            if !self.is_valid_winner(winner) {
+                return Err(Error::StateInconsistent);
+            }
+
+            self.drawing = true;
+
+            self.draw_records.push(DrawRecord {
+                timestamp: self.env().block_timestamp(),
+                block_number: self.env().block_number(),
+                randomness,
+                pool: eligible.iter().map(|i| self.candidates[*i]).collect(),
+                weights,
+                used_fallback,
+                drawn: winner,
+            });
+            let pot = self.total_pot() + self.donations;
+            self.pot_at_draw.push(pot.saturating_sub(self.milestone_released_balance));
+
+            let block = self.env().block_number();
+            self.block_draws = match self.block_draws {
+                (last, draws) if last == block => (block, draws + 1),
+                _ => (block, 1),
+            };
+
+            self.remove_candidate_at(winner_index);
+            if self.is_recipient(winner) && self.winner_beneficiary_policy == WinnerBeneficiaryPolicy::Forfeit {
+                self.env().emit_event(WinForfeited { who: winner });
+            } else {
+                self.winners.push(winner);
+                self.env().emit_event(Draw { winner });
+                self.notify_winner(winner, self.winners.len() - 1);
+            }
+
+            // the raffle ends early when no one left can be drawn.
+            if self.winners.len() == WINNER_COUNT || self.eligible_candidates().is_empty() {
+                self.complete_draws();
+            }
+
+            self.drawing = false;
+
+            Ok(())
+        }
+
+        /// Whether `who` is a live candidate that hasn't already won, checked
+        /// before a draw is committed so a bookkeeping bug can't record a bad winner.
+        fn is_valid_winner(&self, who: AccountId) -> bool {
+            who != AccountId::from([0u8; 32])
+                && self.candidate_set.contains_key(&who)
+                && !self.winners.iter().any(|w| *w == who)
+        }
+
+        /// Draws a winner, or enables refunds if the deposits missed the soft cap.
+        ///
+        /// Passing `expected_round` guards against a transaction meant for an
+        /// earlier round landing after a `reset`.
+        #[ink(message)]
+        pub fn draw(&mut self, expected_round: Option<u32>) -> Result<()> {
+            if matches!(expected_round, Some(round) if round != self.round) {
+                return Err(Error::WrongRound);
+            }
+
+            self.ensure_can_draw()?;
+
+            if self.refund_if_below_soft_cap() {
+                return Ok(());
+            }
+
+            self.draw_winner()
+        }
+
+        /// Enables refunds in place of a draw when the deposits missed the soft
+        /// cap, returning whether it did. Every draw path calls it once the draw
+        /// is otherwise permitted.
+        fn refund_if_below_soft_cap(&mut self) -> bool {
+            if self.cap_status() != CapStatus::Below {
+                return false;
+            }
+            self.enable_refunds();
+            true
+        }
+
+        /// Draws every remaining winner in one call from a single randomness.
+        ///
+        /// Draw `i` picks with `Blake2x256(randomness, i)`, and each winner leaves
+        /// the pool before the next pick, so the winners are distinct. The draw
+        /// gap and per-block limit don't apply between these picks, since they
+        /// all come from the same randomness anyway.
+        #[ink(message)]
+        pub fn draw_all_at_once(&mut self) -> Result<()> {
+            self.ensure_can_draw()?;
+
+            if self.refund_if_below_soft_cap() {
+                return Ok(());
+            }
+
+            if self.drawing {
+                return Err(Error::DrawInProgress);
+            }
+
+            if self.refund_if_no_one_can_win() {
+                return Ok(());
+            }
+
+            if self.eligible_candidates().is_empty() {
+                return Err(Error::NoEligibleCandidates);
+            }
+
+            let (randomness, used_fallback) = self.randomness()?;
+
+            // each pick finishes the raffle once no one left can be drawn.
+            let mut index: u32 = 0;
+            while !self.finished() && !self.awaiting_finalize {
+                let derived = Hash::from(self.env().hash_encoded::<Blake2x256, _>(&(randomness, index)));
+                self.draw_with(derived, used_fallback)?;
+                index += 1;
+            }
+
+            Ok(())
+        }
+
+        /// Lets participants claim their deposits back, donations still go to the beneficiary.
+        fn enable_refunds(&mut self) {
+            self.refunds_enabled = true;
+            self.pending_withdrawal += self.donations;
+            self.donations = 0;
+
+            self.env().emit_event(RefundsEnabled { pot: self.total_pot() });
+        }
+
+        /// Sets how long after the draw window opens refunds unlock if the raffle
+        /// has not finished, `None` removes the deadline.
+        #[ink(message)]
+        pub fn set_refund_deadline(&mut self, deadline: Option<Timestamp>) -> Result<()> {
+            self.ensure_owner()?;
+            self.refund_deadline = deadline;
+            Ok(())
+        }
+
+        /// Returns the time left until stale refunds unlock.
+        ///
+        /// `None` without a deadline, once finished or refunding, or while the
+        /// draw countdown has not started.
+        #[ink(message)]
+        pub fn time_until_refund_deadline(&self) -> Option<Timestamp> {
+            if self.finished() || self.refunds_enabled || self.draw_starts_at == 0 {
+                return None;
+            }
+            let deadline = self.draw_starts_at.saturating_add(self.refund_deadline?);
+            Some(deadline.saturating_sub(self.env().block_timestamp()))
+        }
+
+        /// Enables refunds for a raffle that missed its refund deadline, callable by anyone.
+        ///
+        /// Once a winner is drawn the raffle is no longer stale and has to finish.
+        #[ink(message)]
+        pub fn enable_stale_refunds(&mut self) -> Result<()> {
+            if !self.draw_records.is_empty() || self.awaiting_finalize {
+                return Err(Error::DrawStarted);
+            }
+            if self.time_until_refund_deadline() != Some(0) {
+                return Err(Error::RefundDeadlineNotReached);
+            }
+            self.ensure_nothing_released()?;
+            self.enable_refunds();
+            Ok(())
+        }
+
+        /// Refunds the caller's deposit once refunds are enabled.
+        #[ink(message)]
+        pub fn claim_refund(&mut self) -> Result<()> {
+            if !self.refunds_enabled {
+                return Err(Error::RefundsNotEnabled);
+            }
+
+            let who = self.env().caller();
+            let balance = match self.participants.get(&who) {
+                Some(deposit) => *deposit,
+                None => return Err(Error::HasNotPlayed),
+            };
+
+            self.refund_deposit(who, balance)?;
+
+            self.participants.take(&who);
+            self.tickets.take(&who);
+            self.names.take(&who);
+            self.tags.take(&who);
+            self.charity_votes.take(&who);
+            self.remove_candidate(who);
+            self.add_refunded(who, balance);
+
+            self.env().emit_event(Refunded { who, balance });
+
+            Ok(())
+        }
+
+        /// Cancels the raffle before the first draw and refunds every deposit.
+        ///
+        /// A refund that fails is kept for its account to `retry_refund`, so one
+        /// failing recipient can't block the others. The loop touches every
+        /// participant, so its gas cost grows with the participant count.
+        #[ink(message)]
+        pub fn cancel(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            if self.finished() {
+                return Err(Error::Finished);
+            }
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            if self.refunds_enabled {
+                return Err(Error::RefundsEnabled);
+            }
+            self.ensure_nothing_released()?;
+
+            self.enable_refunds();
+
+            let refunds: Vec<(AccountId, Balance)> =
+                self.participants.iter().map(|(who, deposit)| (*who, *deposit)).collect();
+            for (who, balance) in refunds.into_iter() {
+                self.participants.take(&who);
+                self.tickets.take(&who);
+                self.names.take(&who);
+                self.tags.take(&who);
+                self.charity_votes.take(&who);
+                self.candidate_set.take(&who);
+
+                if self.refund_deposit(who, balance).is_ok() {
+                    self.add_refunded(who, balance);
+                    self.env().emit_event(Refunded { who, balance });
+                } else {
+                    let owed = self.failed_refunds.get(&who).copied().unwrap_or(0);
+                    self.failed_refunds.insert(who, owed + balance);
+                }
+            }
+            self.candidates.clear();
+
+            Ok(())
+        }
+
+        fn add_refunded(&mut self, who: AccountId, amount: Balance) {
+            let refunded = self.refunded.get(&who).copied().unwrap_or(0);
+            self.refunded.insert(who, refunded + amount);
+        }
+
+        /// Returns everything refunded to `who` across all rounds, withdrawn
+        /// entries and returned hidden-entry stakes included.
+        #[ink(message)]
+        pub fn refunded_of(&self, who: AccountId) -> Balance {
+            self.refunded.get(&who).copied().unwrap_or(0)
+        }
+
+        /// Refunds the caller's deposit that `cancel` failed to transfer.
+        #[ink(message)]
+        pub fn retry_refund(&mut self) -> Result<()> {
+            let who = self.env().caller();
+            let balance = match self.failed_refunds.get(&who) {
+                Some(balance) => *balance,
+                None => return Err(Error::NothingToWithdraw),
+            };
+
+            self.refund_deposit(who, balance)?;
+
+            self.failed_refunds.take(&who);
+            self.add_refunded(who, balance);
+
+            self.env().emit_event(Refunded { who, balance });
+
+            Ok(())
+        }
+
+        /// Returns the refund `cancel` failed to transfer to `who`.
+        #[ink(message)]
+        pub fn failed_refund(&self, who: AccountId) -> Balance {
+            self.failed_refunds.get(&who).copied().unwrap_or(0)
+        }
+
+        /// Sets the deposits required for the raffle to be drawn, `None` removes it.
+        ///
+        /// If the deposits are below the soft cap once the draw window opens, `draw`
+        /// enables refunds instead. `pot_cap` acts as the hard cap.
+        #[ink(message)]
+        pub fn set_soft_cap(&mut self, soft_cap: Option<Balance>) -> Result<()> {
+            self.ensure_owner()?;
+            // a missed soft cap refunds deposits a milestone already released.
+            self.ensure_nothing_released()?;
+            self.soft_cap = soft_cap;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn cap_status(&self) -> CapStatus {
+            if self.pot_remaining_to_cap() == Some(0) {
+                return CapStatus::Reached;
+            }
+
+            match self.soft_cap {
+                Some(soft_cap) if self.total_pot() < soft_cap => CapStatus::Below,
+                _ => CapStatus::Met,
+            }
+        }
+
+        /// Sets the pot milestones, each `(threshold, bps)` releasing `bps` of
+        /// `threshold` to the beneficiary once the pot reaches it.
+        ///
+        /// Thresholds must ascend and the shares sum to at most 10000. Milestones
+        /// can only be changed before any is released this round. A raffle paid
+        /// in an entry token gets `UnsupportedForEntryToken`, and a raffle with
+        /// candidate charities can't have milestones, since the recipient is only
+        /// known once it finishes.
+        #[ink(message)]
+        pub fn set_milestones(&mut self, milestones: Vec<(Balance, u16)>) -> Result<()> {
+            self.ensure_owner()?;
+            if self.entry_token.is_some() {
+                return Err(Error::UnsupportedForEntryToken);
+            }
+            self.ensure_nothing_released()?;
+            if !self.charities.is_empty() && !milestones.is_empty() {
+                return Err(Error::InvalidMilestones);
+            }
+
+            let ascending = milestones.windows(2).all(|pair| pair[0].0 < pair[1].0);
+            let total_bps: u32 = milestones.iter().map(|(_, bps)| *bps as u32).sum();
+            if !ascending || total_bps > BPS_DENOMINATOR as u32 {
+                return Err(Error::InvalidMilestones);
+            }
+
+            self.milestones.clear();
+            for milestone in milestones.into_iter() {
+                self.milestones.push(milestone);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn milestones(&self) -> Vec<(Balance, u16)> {
+            self.milestones.iter().copied().collect()
+        }
+
+        /// Releases every milestone the pot has crossed, callable by anyone.
+        ///
+        /// Released balance goes straight to the beneficiary and is left out of
+        /// the final payout. From the first release on, entries can't be withdrawn
+        /// and the raffle can't be cancelled, refunded or have its soft cap
+        /// changed, since part of the deposits is gone. Releases wait until the soft cap, if any, is met.
+        #[ink(message)]
+        pub fn release_milestone(&mut self) -> Result<()> {
+            if self.finished() {
+                return Err(Error::Finished);
+            }
+            if self.refunds_enabled {
+                return Err(Error::RefundsEnabled);
+            }
+            if self.cap_status() == CapStatus::Below {
+                return Err(Error::MilestoneNotReached);
+            }
+
+            let pot = self.total_pot() + self.donations;
+            let mut balance: Balance = 0;
+            let mut released = self.milestones_released;
+            while let Some((threshold, bps)) = self.milestones.get(released).copied() {
+                if pot < threshold {
+                    break;
+                }
+                balance += threshold * bps as Balance / BPS_DENOMINATOR as Balance;
+                released += 1;
+            }
+            if released == self.milestones_released {
+                return Err(Error::MilestoneNotReached);
+            }
+
+            balance = self.payable(balance);
+            if balance > 0 && self.env().transfer(self.beneficiary, balance).is_err() {
+                return Err(Error::TransferFailed);
+            }
+            self.milestone_released_balance += balance;
+            self.lifetime_donated += balance;
+            self.milestones_released = released;
+            self.env().emit_event(MilestoneReleased { released, balance });
+
+            Ok(())
+        }
+
+        fn ensure_nothing_released(&self) -> Result<()> {
+            if self.milestones_released > 0 {
+                return Err(Error::MilestoneReleased);
+            }
+            Ok(())
+        }
+
+        /// Mints a prize token for every winner, failed mints are left for the winner to claim.
+        fn mint_prizes(&mut self) {
+            if let PrizeMode::Nft { collection } = self.prize_mode {
+                let winners: Vec<AccountId> = self.winners.iter().copied().collect();
+                for (index, winner) in winners.into_iter().enumerate() {
+                    // unique across rounds, the round in the high bits and the winner ordinal below.
+                    let id = (self.round as u128) << 32 | index as u128;
+                    if self.mint_prize(collection, winner, id).is_err() {
+                        self.pending_prizes.insert((self.round, winner), (collection, id));
+                    }
+                }
+            }
+        }
+
+        fn mint_prize(&self, collection: AccountId, to: AccountId, id: u128) -> Result<()> {
+            build_call::<Environment>()
+                .callee(collection)
+                .exec_input(
+                    // `(4, id)` encodes like the PSP34 `Id::U128(id)` variant.
+                    ExecutionInput::new(Selector::new(PSP34_MINT_SELECTOR))
+                        .push_arg(to)
+                        .push_arg((4u8, id)),
+                )
+                .returns::<ReturnType<core::result::Result<(), Psp34Error>>>()
+                .fire()
+                .map_err(|_| Error::PrizeMintFailed)?
+                .map_err(|_| Error::PrizeMintFailed)
+        }
+
+        /// Retries minting the caller's prize token of `round` after a failed mint.
+        ///
+        /// The token comes from the collection of that round, even if the prize
+        /// mode has changed since.
+        #[ink(message)]
+        pub fn claim_prize(&mut self, round: u32) -> Result<()> {
+            let who = self.env().caller();
+            let (collection, id) = match self.pending_prizes.get(&(round, who)) {
+                Some(prize) => *prize,
+                None => return Err(Error::NoPendingPrize),
+            };
+
+            self.mint_prize(collection, who, id)?;
+            self.pending_prizes.take(&(round, who));
+            Ok(())
+        }
+
+        /// Sets what winners receive, only before the first draw.
+        #[ink(message)]
+        pub fn set_prize_mode(&mut self, prize_mode: PrizeMode) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.prize_mode = prize_mode;
+            Ok(())
+        }
+
+        /// Sends PSP22 tokens accidentally transferred to this contract to `to`.
+        ///
+        /// The entry token holds the participants' deposits, so it is rejected
+        /// with `CannotRescueEntryToken`.
+        #[ink(message)]
+        pub fn rescue_token(&mut self, token: AccountId, to: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+
+            if self.entry_token == Some(token) {
+                return Err(Error::CannotRescueEntryToken);
+            }
+
+            self.transfer_token(token, to, amount)?;
+
+            self.env().emit_event(TokenRescued { token, to, amount });
+
+            Ok(())
+        }
+
+        /// Starts a new round once the current one has finished, or refunded every
+        /// deposit, and been paid out.
+        ///
+        /// Entries, winners, donations and the seed commitment are cleared, while
+        /// the configuration and lifetime totals are kept.
+        #[ink(message)]
+        pub fn reset(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+
+            // a refunding round is over once every deposit has been claimed.
+            let refunded = self.refunds_enabled && self.participants.is_empty();
+            if !self.finished() && !refunded {
+                return Err(Error::NotFinished);
+            }
+
+            if !self.payout_complete() {
+                return Err(Error::PayoutPending);
+            }
+
+            let accounts: Vec<AccountId> = self.participants.keys().copied().collect();
+            for who in accounts.iter() {
+                self.participants.take(who);
+                self.tickets.take(who);
+            }
+            // names and tags are cleared by their own keys, in case an entry left without them.
+            let named: Vec<AccountId> = self.names.keys().copied().collect();
+            for who in named.iter() {
+                self.names.take(who);
+            }
+            let tagged: Vec<AccountId> = self.tags.keys().copied().collect();
+            for who in tagged.iter() {
+                self.tags.take(who);
+            }
+            let voters: Vec<AccountId> = self.charity_votes.keys().copied().collect();
+            for who in voters.iter() {
+                self.charity_votes.take(who);
+            }
+            let withdrawn: Vec<AccountId> = self.last_withdraw_at.keys().copied().collect();
+            for who in withdrawn.iter() {
+                self.last_withdraw_at.take(who);
+            }
+            let acted: Vec<AccountId> = self.last_action_at.keys().copied().collect();
+            for who in acted.iter() {
+                self.last_action_at.take(who);
+            }
+            let contributors: Vec<AccountId> = self.contributions.keys().copied().collect();
+            for who in contributors.iter() {
+                self.contributions.take(who);
+            }
+            let candidates: Vec<AccountId> = self.candidates.iter().copied().collect();
+            for who in candidates.iter() {
+                self.candidate_set.take(who);
+            }
+            self.candidates.clear();
+            self.winners.clear();
+            self.draw_records.clear();
+            self.pot_at_draw.clear();
+            self.entry_feed.clear();
+            self.shuffled = false;
+            self.entries_closed = false;
+
+            self.draw_starts_at = 0;
+            self.threshold_reached = false;
+            self.used_fallback = false;
+            self.refunds_enabled = false;
+            self.beneficiary_ack = false;
+            self.donations = self.rollover_balance;
+            self.rollover_balance = 0;
+            self.plays_count = 0;
+            self.total_played_volume = 0;
+            self.milestones_released = 0;
+            self.milestone_released_balance = 0;
+            self.round_recipient = None;
+            self.seed_commitment = None;
+            self.revealed_seed = None;
+            self.winner_root = None;
+            self.finished_at = None;
+            self.round += 1;
+
+            self.env().emit_event(NewRound { round: self.round });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn round(&self) -> u32 {
+            self.round
+        }
+
+        /// Returns the total paid out to beneficiaries over all rounds.
+        #[ink(message)]
+        pub fn total_donated_lifetime(&self) -> Balance {
+            self.lifetime_donated
+        }
+
+        /// Returns how many entries this round had and the sum of their opening
+        /// deposits, matching the round's `Played` events. Withdrawals don't
+        /// lower it and deposit increases don't raise it.
+        #[ink(message)]
+        pub fn play_tally(&self) -> (u64, Balance) {
+            (self.plays_count, self.total_played_volume)
+        }
+
+        /// Returns how many entries were made in the current block.
+        ///
+        /// A spike right before the draw can hint at entries timed to game it.
+        #[ink(message)]
+        pub fn entries_this_block(&self) -> u32 {
+            match self.block_entries {
+                (block, entries) if block == self.env().block_number() => entries,
+                _ => 0,
+            }
+        }
+
+        /// Returns the `play_tally` summed over all rounds.
+        #[ink(message)]
+        pub fn lifetime_play_tally(&self) -> (u64, Balance) {
+            (self.lifetime_plays_count, self.lifetime_played_volume)
+        }
+
+        /// Transfers the pending payout to the beneficiary.
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<()> {
+            self.withdraw_amount(0)
+        }
+
+        /// Transfers `amount` of the pending payout to the beneficiary, zero meaning all of it.
+        ///
+        /// Like `withdraw`, the transfer is capped so the contract keeps its reserve.
+        #[ink(message)]
+        pub fn withdraw_amount(&mut self, amount: Balance) -> Result<()> {
+            if self.env().caller() != self.beneficiary {
+                return Err(Error::NotBeneficiary);
+            }
+
+            if amount > self.pending_withdrawal {
+                return Err(Error::AmountExceedsPending);
+            }
+
+            let requested = if amount == 0 { self.pending_withdrawal } else { amount };
+            let balance = self.payable(requested);
+            if balance == 0 {
+                return Err(Error::NothingToWithdraw);
+            }
+
+            if self.env().transfer(self.beneficiary, balance).is_err() {
+                return Err(Error::TransferFailed);
+            }
+            self.pending_withdrawal -= balance;
+            self.lifetime_donated += balance;
+
+            self.env().emit_event(PayoutWithdrawn { beneficiary: self.beneficiary, balance });
+
+            Ok(())
+        }
+
+        /// Transfers the round's payout to the charity that won the vote, when the
+        /// finishing draw didn't push it.
+        #[ink(message)]
+        pub fn withdraw_charity_payout(&mut self) -> Result<()> {
+            let recipient = match self.round_recipient {
+                Some(recipient) if recipient == self.env().caller() => recipient,
+                _ => return Err(Error::NotBeneficiary),
+            };
+
+            let balance = self.payable(self.recipient_pending);
+            if balance == 0 {
+                return Err(Error::NothingToWithdraw);
+            }
+
+            if self.env().transfer(recipient, balance).is_err() {
+                return Err(Error::TransferFailed);
+            }
+            self.recipient_pending -= balance;
+            self.lifetime_donated += balance;
+
+            self.env().emit_event(PayoutWithdrawn { beneficiary: recipient, balance });
+
+            Ok(())
+        }
+
+        /// Returns who receives this round's payout: the leading charity, if any
+        /// entry voted, otherwise the beneficiary.
+        #[ink(message)]
+        pub fn payout_recipient(&self) -> AccountId {
+            if let Some(recipient) = self.round_recipient {
+                return recipient;
+            }
+            if self.finished() {
+                return self.beneficiary;
+            }
+            self.leading_charity().unwrap_or(self.beneficiary)
+        }
+
+        /// Returns the round's payout the winning charity has yet to withdraw.
+        #[ink(message)]
+        pub fn charity_payout_pending(&self) -> Balance {
+            self.recipient_pending
+        }
+
+        /// Returns whether `who` is the beneficiary or a candidate charity, either
+        /// of which could receive the pot.
+        fn is_recipient(&self, who: AccountId) -> bool {
+            who == self.beneficiary || self.charities.iter().any(|c| *c == who)
+        }
+
+        /// Confirms on-chain that the beneficiary received this round's payout.
+        #[ink(message)]
+        pub fn acknowledge_receipt(&mut self) -> Result<()> {
+            if self.env().caller() != self.beneficiary {
+                return Err(Error::NotBeneficiary);
+            }
+
+            if !self.finished() {
+                return Err(Error::NotFinished);
+            }
+
+            if !self.payout_complete() {
+                return Err(Error::PayoutPending);
+            }
+
+            self.beneficiary_ack = true;
+            self.env().emit_event(ReceiptAcknowledged { at: self.env().block_timestamp() });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn beneficiary_acknowledged(&self) -> bool {
+            self.beneficiary_ack
+        }
+
+        /// Caps `amount` so a payout never takes the contract below its reserve
+        /// plus the rollover it holds for the next round.
+        fn payable(&self, amount: Balance) -> Balance {
+            let held = self.reserve + self.rollover_balance;
+            core::cmp::min(amount, self.env().balance().saturating_sub(held))
+        }
+
+        /// Sets the basis points of each finished pot kept to seed the next round.
+        ///
+        /// The kept part is credited as a donation when the owner calls `reset`.
+        /// A raffle paid in an entry token gets `UnsupportedForEntryToken`.
+        #[ink(message)]
+        pub fn set_rollover_bps(&mut self, rollover_bps: u16) -> Result<()> {
+            self.ensure_owner()?;
+            if self.entry_token.is_some() {
+                return Err(Error::UnsupportedForEntryToken);
+            }
+            if rollover_bps > BPS_DENOMINATOR {
+                return Err(Error::InvalidBps);
+            }
+            self.rollover_bps = rollover_bps;
+            Ok(())
+        }
+
+        /// Returns how a finished pot is split.
+        ///
+        /// The rollover is the only configurable share, `set_rollover_bps` keeps
+        /// it within 10000 so the beneficiary share never goes negative.
+        #[ink(message)]
+        pub fn splits(&self) -> Splits {
+            Splits {
+                rollover_bps: self.rollover_bps,
+                beneficiary_bps: BPS_DENOMINATOR - self.rollover_bps,
+            }
+        }
+
+        /// Returns what the last finished pot kept for the next round.
+        #[ink(message)]
+        pub fn rollover_balance(&self) -> Balance {
+            self.rollover_balance
+        }
+
+        /// Sets the unit deposits and increases have to be a multiple of, 0 or 1 for any amount.
+        #[ink(message)]
+        pub fn set_deposit_increment(&mut self, increment: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.deposit_increment = increment;
+            Ok(())
+        }
+
+        /// Sets whether deposits of exactly `MIN_DEPOSIT` or `MAX_DEPOSIT` are valid.
+        ///
+        /// Bounds are inclusive by default; exclusive bounds only accept deposits
+        /// strictly between them.
+        #[ink(message)]
+        pub fn set_bounds_inclusive(&mut self, inclusive: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.bounds_inclusive = inclusive;
+            Ok(())
+        }
+
+        /// Sets whether the first draw fixes the pool, only before the first draw.
+        ///
+        /// Once fixed, new entries and deposit increases are rejected, so later
+        /// draws pick from exactly the accounts and weights the first one saw,
+        /// less the winners. Withdrawals already stop at the first draw.
+        #[ink(message)]
+        pub fn set_snapshot_on_first_draw(&mut self, snapshot: bool) -> Result<()> {
+            self.ensure_owner()?;
+            if !self.draw_records.is_empty() {
+                return Err(Error::DrawStarted);
+            }
+            self.snapshot_on_first_draw = snapshot;
+            Ok(())
+        }
+
+        /// Sets the balance payouts leave in the contract.
+        ///
+        /// An account that falls below the existential deposit is reaped, which
+        /// would destroy the contract, so the reserve defaults to that deposit.
+        /// Payouts the reserve holds back stay pending until the contract's
+        /// balance covers them.
+        #[ink(message)]
+        pub fn set_reserve(&mut self, reserve: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.reserve = reserve;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn reserve(&self) -> Balance {
+            self.reserve
+        }
+
+        /// Returns whether the round is over and nothing is left to claim or settle.
+        ///
+        /// Unlike `finished`, this also waits for the payout, failed refunds,
+        /// unrevealed hidden entries and unminted prizes. A round that refunded
+        /// every deposit counts as over, a round waiting for `finalize` doesn't.
+        #[ink(message)]
+        pub fn is_terminal(&self) -> bool {
+            let over = self.finished() || (self.refunds_enabled && self.participants.is_empty());
+            over && self.payout_complete()
+                && self.failed_refunds.is_empty()
+                && self.entry_commitments.is_empty()
+                && self.pending_prizes.is_empty()
+        }
+
+        /// Returns whether the beneficiary, and any winning charity, has received every payout.
+        #[ink(message)]
+        pub fn payout_complete(&self) -> bool {
+            self.pending_withdrawal == 0 && self.pending_token_withdrawal == 0 && self.recipient_pending == 0
+        }
+
+        /// Returns the payout the beneficiary has yet to withdraw.
+        #[ink(message)]
+        pub fn pending_withdrawal(&self) -> Balance {
+            self.pending_withdrawal
+        }
+
+        /// Transfers the pending payout in the entry token to the beneficiary.
+        #[ink(message)]
+        pub fn withdraw_tokens(&mut self) -> Result<()> {
+            if self.env().caller() != self.beneficiary {
+                return Err(Error::NotBeneficiary);
+            }
+
+            let token = match self.entry_token {
+                Some(token) => token,
+                None => return Err(Error::NoEntryToken),
+            };
+            let balance = self.pending_token_withdrawal;
+            if balance == 0 {
+                return Err(Error::NothingToWithdraw);
+            }
+
+            self.transfer_token(token, self.beneficiary, balance)?;
+            self.pending_token_withdrawal = 0;
+
+            self.env().emit_event(TokenPaidOut { token, beneficiary: self.beneficiary, balance });
+
+            Ok(())
+        }
+
+        /// Returns the payout in the entry token the beneficiary has yet to withdraw.
+        #[ink(message)]
+        pub fn pending_token_withdrawal(&self) -> Balance {
+            self.pending_token_withdrawal
+        }
+
+        /// Returns the PSP22 token entries are paid in, `None` for the native currency.
+        #[ink(message)]
+        pub fn entry_token(&self) -> Option<AccountId> {
+            self.entry_token
+        }
+
+        /// Returns the entry token balance this contract holds, asked from the
+        /// token with `balance_of`, or zero if that call fails.
+        ///
+        /// Without an entry token the deposits are native, so this is `total_pot`.
+        #[ink(message)]
+        pub fn token_pot(&self) -> Balance {
+            match self.entry_token {
+                Some(token) => self.token_balance(token).unwrap_or(0),
+                None => self.total_pot(),
+            }
+        }
+
+        /// Sets whether the payout is transferred to the beneficiary by the finishing draw.
+        ///
+        /// Pushing saves the beneficiary a `withdraw` transaction, but runs the
+        /// transfer inside the draw; by default the payout waits for `withdraw`.
+        #[ink(message)]
+        pub fn set_push_payout(&mut self, push_payout: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.push_payout = push_payout;
+            Ok(())
+        }
+
+        /// Checks every precondition of `draw` without drawing.
+        ///
+        /// Returns the error `draw` would fail with, apart from `RandomnessUnavailable`
+        /// which is only known once the randomness is fetched.
+        #[ink(message)]
+        pub fn draw_precheck(&self) -> Result<()> {
+            self.ensure_can_draw()
+        }
+
+        /// Draws a winner if `auto_draw` is set and a draw is permitted, otherwise does nothing.
+        #[ink(message)]
+        pub fn poke(&mut self) -> Result<()> {
+            if self.auto_draw && self.ensure_can_draw().is_ok() && !self.refund_if_below_soft_cap() {
+                return self.draw_winner();
+            }
+            Ok(())
+        }
+
+        /// Sets whether `play` and `poke` draw a winner once a draw is permitted.
+        #[ink(message)]
+        pub fn set_auto_draw(&mut self, auto_draw: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.auto_draw = auto_draw;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn winners(&self) -> (Option<AccountId>, Option<AccountId>) {
+            let first = self.winners.first().map(|w| w.clone());
+            let last = self.winners.last().map(|w| w.clone());
+            return (first, last)
+        }
+
+        /// Returns when the raffle finished, or when it would if every draw were
+        /// made as soon as it is allowed. `None` before the countdown starts.
+        #[ink(message)]
+        pub fn projected_finish(&self) -> Option<Timestamp> {
+            if self.finished_at.is_some() {
+                return self.finished_at;
+            }
+
+            if self.draw_starts_at == 0 {
+                return None;
+            }
+
+            let draws_left = WINNER_COUNT.saturating_sub(self.winners.len()).saturating_sub(1);
+            let next = self.next_draw_at().max(self.env().block_timestamp());
+            Some(next + draws_left as Timestamp * self.draw_gap)
+        }
+
+        #[ink(message)]
+        pub fn is_winner(&self, who: AccountId) -> bool {
+            self.winners.iter().any(|w| *w == who)
+        }
+
+        /// Returns up to `len` entries starting at `start`, in entry order.
+        #[ink(message)]
+        pub fn entry_feed_page(&self, start: u32, len: u32) -> Vec<(AccountId, Timestamp)> {
+            self.entry_feed.iter().skip(start as usize).take(len as usize).copied().collect()
+        }
+
+        /// Returns the raffle config and winners with up to `len` participants starting at `start`.
+        ///
+        /// Pages follow the storage order of the participants, which only stays
+        /// the same while no one enters or leaves.
+        #[ink(message)]
+        pub fn export_snapshot(&self, start: u32, len: u32) -> Snapshot {
+            Snapshot {
+                storage_version: self.storage_version,
+                owner: self.owner,
+                beneficiary: self.beneficiary,
+                round: self.round,
+                min_players: self.min_players,
+                max_participants: self.max_participants,
+                pot_cap: self.pot_cap,
+                soft_cap: self.soft_cap,
+                donations: self.donations,
+                participant_count: self.participants.len(),
+                participants: self
+                    .participants
+                    .iter()
+                    .skip(start as usize)
+                    .take(len as usize)
+                    .map(|(who, deposit)| (*who, *deposit))
+                    .collect(),
+                winners: self.winners.iter().copied().collect(),
+            }
+        }
+
+        /// Returns the winners of up to `count` finished rounds starting at `start_round`.
+        ///
+        /// Rounds that ended in refunds have no winners and are skipped, `count`
+        /// is capped at `MAX_BATCH_SIZE`.
+        #[ink(message)]
+        pub fn all_winners(&self, start_round: u32, count: u32) -> Vec<(u32, Vec<AccountId>)> {
+            let count = core::cmp::min(count as usize, MAX_BATCH_SIZE) as u32;
+            (start_round..start_round.saturating_add(count))
+                .filter_map(|round| self.round_winners.get(&round).map(|winners| (round, winners.clone())))
+                .collect()
+        }
+
+        /// Returns up to `len` returned overpayments starting at `start`, oldest first.
+        #[ink(message)]
+        pub fn overpayment_log_page(&self, start: u32, len: u32) -> Vec<(AccountId, Balance)> {
+            self.overpayment_log.iter().skip(start as usize).take(len as usize).copied().collect()
+        }
+
+        /// Returns up to `len` winners starting at `start`, in draw order.
+        #[ink(message)]
+        pub fn winners_page(&self, start: u32, len: u32) -> Vec<AccountId> {
+            self.winners.iter().skip(start as usize).take(len as usize).copied().collect()
+        }
+
+        /// Returns the zero-based index of the next winner to be drawn, `None` once finished.
+        #[ink(message)]
+        pub fn next_winner_ordinal(&self) -> Option<u32> {
+            if self.finished() || self.awaiting_finalize {
+                return None;
+            }
+            Some(self.winners.len())
+        }
+
+        /// Returns how many winners are left to draw, zero once finished.
+        #[ink(message)]
+        pub fn draws_remaining(&self) -> u32 {
+            if self.finished() || self.awaiting_finalize {
+                return 0;
+            }
+            WINNER_COUNT.saturating_sub(self.winners.len())
+        }
+
+        /// Returns roughly how many candidate iterations `draw_all_at_once` would perform.
+        ///
+        /// Each draw scans the remaining candidates for eligibility and, unless odds
+        /// are equal, walks their weights once more, and a pending shuffle adds one
+        /// pass. This is an approximate heuristic for judging whether the batch fits
+        /// in a block, not a gas figure.
+        #[ink(message)]
+        pub fn estimate_draw_all_cost(&self) -> u32 {
+            let candidates = self.candidates.len();
+            let draws = self.draws_remaining().min(candidates);
+            let passes = if self.weighting == Weighting::Equal { 1 } else { 2 };
+
+            let mut cost: u32 = 0;
+            if self.shuffle_before_draw && !self.shuffled {
+                cost = cost.saturating_add(candidates);
+            }
+            for draw in 0..draws {
+                cost = cost.saturating_add((candidates - draw).saturating_mul(passes));
+            }
+            cost
+        }
+
+        #[ink(message)]
+        pub fn beneficiary(&self) -> AccountId {
+            self.beneficiary
+        }
+
+        /// Returns the payout `who` would get from the current pot and donations,
+        /// `None` if `who` is not the payout recipient.
+        ///
+        /// The payout, less milestone releases and the rollover, goes to a single
+        /// recipient, see `payout_recipient`.
+        #[ink(message)]
+        pub fn beneficiary_share(&self, who: AccountId) -> Option<Balance> {
+            if who != self.payout_recipient() {
+                return None;
+            }
+            Some(self.payout_split().0)
+        }
+
+        #[ink(message)]
+        pub fn get_beneficiary(&self) -> AccountId {
+            self.beneficiary
+        }
+
+        #[ink(message)]
+        pub fn get_owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// Returns whether the caller owns the raffle.
+        #[ink(message)]
+        pub fn am_i_owner(&self) -> bool {
+            self.env().caller() == self.owner
+        }
+
+        /// Starts handing the beneficiary role over to `beneficiary`.
+        ///
+        /// The owner proposes the new beneficiary, or the current beneficiary does
+        /// when `beneficiary_controls_handoff` is set. The change only completes
+        /// once `beneficiary` calls `accept_beneficiary`, so a mistyped address
+        /// can't receive the payout.
+        #[ink(message)]
+        pub fn set_beneficiary(&mut self, beneficiary: AccountId) -> Result<()> {
+            self.ensure_handoff_authority()?;
+            self.pending_beneficiary = Some(beneficiary);
+            self.env().emit_event(BeneficiaryTransferStarted {
+                beneficiary: self.beneficiary,
+                pending_beneficiary: beneficiary,
+            });
+            Ok(())
+        }
+
+        /// Completes a pending beneficiary transfer, called by the pending beneficiary.
+        #[ink(message)]
+        pub fn accept_beneficiary(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            if self.pending_beneficiary != Some(caller) {
+                return Err(Error::NotPendingBeneficiary);
+            }
+
+            let previous_beneficiary = self.beneficiary;
+            self.beneficiary = caller;
+            self.pending_beneficiary = None;
+            self.env().emit_event(BeneficiaryChanged { previous_beneficiary, new_beneficiary: caller });
+            Ok(())
+        }
+
+        /// Cancels a pending beneficiary transfer.
+        #[ink(message)]
+        pub fn cancel_beneficiary_transfer(&mut self) -> Result<()> {
+            self.ensure_handoff_authority()?;
+            self.pending_beneficiary.take().ok_or(Error::NoPendingBeneficiary)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pending_beneficiary(&self) -> Option<AccountId> {
+            self.pending_beneficiary
+        }
+
+        /// Sets whether the beneficiary, rather than the owner, proposes and
+        /// cancels beneficiary transfers.
+        #[ink(message)]
+        pub fn set_beneficiary_controls_handoff(&mut self, controls: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.beneficiary_controls_handoff = controls;
+            Ok(())
+        }
+
+        fn ensure_handoff_authority(&self) -> Result<()> {
+            if !self.beneficiary_controls_handoff {
+                return self.ensure_owner();
+            }
+            if self.env().caller() != self.beneficiary {
+                return Err(Error::NotBeneficiary);
+            }
+            Ok(())
+        }
+
+        /// Starts handing the raffle configuration over to `new_owner`.
+        ///
+        /// The transfer only completes once `new_owner` calls `accept_ownership`.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.pending_owner = Some(new_owner);
+            self.env().emit_event(OwnershipTransferStarted { owner: self.owner, pending_owner: new_owner });
+            Ok(())
+        }
+
+        /// Completes a pending ownership transfer, called by the pending owner.
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+            if self.pending_owner != Some(caller) {
+                return Err(Error::NotPendingOwner);
+            }
+
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+            self.env().emit_event(OwnershipTransferred { previous_owner, new_owner: caller });
+            Ok(())
+        }
+
+        /// Cancels a pending ownership transfer.
+        #[ink(message)]
+        pub fn cancel_ownership_transfer(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let pending_owner = self.pending_owner.take().ok_or(Error::NoPendingOwner)?;
+            self.env().emit_event(OwnershipTransferCancelled { owner: self.owner, pending_owner });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner
+        }
+
+        /// Returns the entropy accumulated from the entries so far.
+        #[ink(message)]
+        pub fn entropy_accumulator(&self) -> [u8; 32] {
+            self.entropy_accumulator
+        }
+
+        /// Returns the sum of all deposits.
+        #[ink(message)]
+        pub fn total_pot(&self) -> Balance {
+            self.participants.values().sum()
+        }
+
+        /// Returns whether the contract's balance covers every deposit and owed
+        /// refund while keeping its reserve.
+        ///
+        /// A raffle paid in an entry token holds the deposits in the token, so
+        /// its token balance is queried for them.
+        #[ink(message)]
+        pub fn can_refund_all(&self) -> bool {
+            let owed = self.total_pot() + self.failed_refunds.values().sum::<Balance>();
+            match self.entry_token {
+                Some(token) => {
+                    self.env().balance() >= self.reserve
+                        && matches!(self.token_balance(token), Some(held) if held >= owed)
+                }
+                None => self.env().balance() >= owed + self.reserve,
+            }
+        }
+
+        /// Returns the contract balance left after every obligation: the reserve,
+        /// the rollover, the pending payout, failed refunds, hidden entry stakes
+        /// and, once refunds are enabled, the deposits.
+        ///
+        /// What is left is the pot still bound for the beneficiary plus any
+        /// surplus transferred in outside the raffle. A raffle paid in an entry
+        /// token owes its deposits and failed refunds in the token, so they
+        /// don't count against the native balance.
+        #[ink(message)]
+        pub fn uncommitted_balance(&self) -> Balance {
+            let native_deposits = self.entry_token.is_none();
+            let refundable = if self.refunds_enabled && native_deposits { self.total_pot() } else { 0 };
+            let failed_refunds = if native_deposits { self.failed_refunds.values().sum::<Balance>() } else { 0 };
+            let obligations = self.reserve
+                + self.rollover_balance
+                + self.pending_withdrawal
+                + self.recipient_pending
+                + failed_refunds
+                + self.entry_commitments.values().map(|(_, stake, _)| *stake).sum::<Balance>()
+                + refundable;
+            self.env().balance().saturating_sub(obligations)
+        }
+
+        /// Returns the participant that contributed the most this round with its total.
+        ///
+        /// Accounts that withdrew or were refunded are skipped. Ties go to the
+        /// account found first in storage order.
+        #[ink(message)]
+        pub fn top_contributor(&self) -> Option<(AccountId, Balance)> {
+            let mut top: Option<(AccountId, Balance)> = None;
+            for (who, amount) in self.contributions.iter() {
+                if !self.participants.contains_key(who) {
+                    continue;
+                }
+                if !matches!(top, Some((_, best)) if *amount <= best) {
+                    top = Some((*who, *amount));
+                }
+            }
+            top
+        }
+
+        /// Returns the average deposit per participant, zero without participants.
+        ///
+        /// Every participant holds a single entry, so this is also the average per entry.
+        #[ink(message)]
+        pub fn average_deposit(&self) -> Balance {
+            self.total_pot().checked_div(self.participants.len() as Balance).unwrap_or(0)
+        }
+
+        /// Returns the smallest and largest deposits, `None` without participants.
+        #[ink(message)]
+        pub fn deposit_range(&self) -> Option<(Balance, Balance)> {
+            self.participants.values().fold(None, |range, d| match range {
+                None => Some((*d, *d)),
+                Some((min, max)) => Some((min.min(*d), max.max(*d))),
+            })
+        }
+
+        #[ink(message)]
+        pub fn stats(&self) -> RaffleStats {
+            RaffleStats {
+                unique_participants: self.participants.len(),
+                total_tickets: self.ticket_count(),
+                total_pot: self.total_pot(),
+                total_donations: self.donations,
+                winners_drawn: self.winners.len(),
+                rounds_completed: self.rounds_completed,
+            }
+        }
+
+        /// Publishes the Merkle root of the winners.
+        ///
+        /// Leaves are the Blake2x256 hash of the encoded winner account, and each
+        /// parent is the Blake2x256 hash of its two children in ascending order.
+        #[ink(message)]
+        pub fn publish_winner_root(&mut self, root: [u8; 32]) -> Result<()> {
+            self.ensure_owner()?;
+            self.winner_root = Some(root);
+            Ok(())
+        }
+
+        /// Checks whether `who` is included in the published winner root.
+        #[ink(message)]
+        pub fn verify_winner(&self, who: AccountId, proof: Vec<[u8; 32]>) -> bool {
+            let root = match self.winner_root {
+                Some(root) => root,
+                None => return false,
+            };
+
+            let leaf = self.env().hash_encoded::<Blake2x256, _>(&who);
+            let computed = proof.iter().fold(leaf, |node, sibling| {
+                if node <= *sibling {
+                    self.env().hash_encoded::<Blake2x256, _>(&(node, *sibling))
+                } else {
+                    self.env().hash_encoded::<Blake2x256, _>(&(*sibling, node))
+                }
+            });
+            computed == root
+        }
+
+        /// Returns the default draw countdown and minimum player count.
+        #[ink(message)]
+        pub fn defaults(&self) -> (Timestamp, u32) {
+            (DRAW_COUNTDOWN, MINI_PLAYER_COUNT)
+        }
+
+        /// Returns the number of distinct accounts that can still be drawn.
+        #[ink(message)]
+        pub fn distinct_candidate_accounts(&self) -> u32 {
+            self.candidate_set.len()
+        }
+
+        /// Returns each distinct account that can still be drawn, drawn and
+        /// withdrawn accounts excluded.
+        #[ink(message)]
+        pub fn remaining_finalists(&self) -> Vec<AccountId> {
+            self.candidate_set.keys().copied().collect()
+        }
+
+        /// Returns whether `who` can still be drawn as a winner.
+        #[ink(message)]
+        pub fn is_candidate(&self, who: AccountId) -> bool {
+            self.candidate_set.contains_key(&who)
+        }
+
+        /// Returns each remaining candidate with its percentage chance to win the next draw.
+        ///
+        /// Eligible candidates share the odds by weight, or equally under
+        /// `Weighting::Equal`, excluded candidates get zero. Percentages are rounded down
+        /// and the leftover points go to the earliest eligible candidates, so they
+        /// sum to exactly 100.
+        #[ink(message)]
+        pub fn odds_distribution(&self) -> Vec<(AccountId, u32)> {
+            let eligible = self.eligible_candidates();
+            let mut odds: Vec<(AccountId, u32)> = self.candidates.iter().map(|c| (*c, 0)).collect();
+            if eligible.is_empty() {
+                return odds;
+            }
+
+            let mut weights = self.candidate_weights(&eligible);
+            if weights.is_empty() {
+                weights = eligible.iter().map(|_| 1).collect();
+            }
+            let total: u64 = weights.iter().map(|w| *w as u64).sum();
+            let mut given = 0;
+            for (index, weight) in eligible.iter().zip(weights.iter()) {
+                let share = (*weight as u64 * 100 / total) as u32;
+                odds[*index as usize].1 = share;
+                given += share;
+            }
+            for index in eligible.iter().take((100 - given) as usize) {
+                odds[*index as usize].1 += 1;
+            }
+            odds
+        }
+
+        /// Returns the summed draw weight of the eligible candidates.
+        ///
+        /// Under `Weighting::Equal` this is the eligible candidate count.
+        #[ink(message)]
+        pub fn total_candidate_weight(&self) -> Balance {
+            self.eligible_candidates()
+                .iter()
+                .map(|i| self.weight_of(self.candidates[*i]) as Balance)
+                .sum()
+        }
+
+        /// Returns the tickets held by `who`, the draw weight under `Weighting::Tickets`.
+        #[ink(message)]
+        pub fn tickets_of(&self, who: AccountId) -> u32 {
+            self.tickets.get(&who).copied().unwrap_or(0)
+        }
+
+        /// Returns the tickets held across all participants.
+        #[ink(message)]
+        pub fn ticket_count(&self) -> u32 {
+            self.tickets.values().sum()
+        }
+
+        #[ink(message)]
+        pub fn fairness_report(&self) -> FairnessReport {
+            FairnessReport {
+                hash_algorithm: HashAlgorithm::Blake2x128,
+                used_fallback: self.used_fallback,
+                randomness_oracle: self.randomness_oracle.is_some(),
+                commit_reveal: self.seed_commitment.is_some(),
+                draws: self.draw_records.len(),
+                weighting: self.weighting,
+            }
+        }
+
+        /// Returns whether a draw is permitted now, and why not.
+        ///
+        /// The status is derived from the same checks `draw` makes, so `Ready`
+        /// means `draw` gets past them.
+        #[ink(message)]
+        pub fn draw_status(&self) -> DrawStatus {
+            let error = match self.ensure_can_draw() {
+                Ok(()) => return DrawStatus::Ready,
+                Err(error) => error,
+            };
+
+            let needed = self.min_players.saturating_sub(self.participants.len());
+            match error {
+                Error::Finished => DrawStatus::Finished,
+                Error::AwaitingFinalize => DrawStatus::AwaitingFinalize,
+                Error::RefundsEnabled => DrawStatus::Refunding,
+                Error::DrawPaused => DrawStatus::Paused,
+                // the countdown only starts once enough players joined.
+                Error::DrawNotStarted | Error::DrawTooSoon if needed > 0 => DrawStatus::NotEnoughPlayers { needed },
+                Error::DrawNotStarted | Error::DrawTooSoon => {
+                    let remaining = self.next_draw_at().saturating_sub(self.env().block_timestamp());
+                    DrawStatus::InCountdown { remaining }
+                }
+                Error::NotEnoughPlayer => DrawStatus::NotEnoughPlayers { needed },
+                reason => DrawStatus::Blocked { reason },
+            }
+        }
+
+        /// Returns the layout version of this storage.
+        ///
+        /// Every field is loaded eagerly, so code with a different layout can't read
+        /// this storage at all and there is no in-place migration: an upgrade that
+        /// changes the layout needs a fresh deployment, and the version only tells
+        /// callers which layout a raffle was deployed with.
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        #[ink(message)]
+        pub fn event_schema_version(&self) -> u16 {
+            EVENT_SCHEMA_VERSION
+        }
+
+        #[ink(message)]
+        pub fn created_at_time(&self) -> Timestamp {
+            self.created_at
+        }
+
+        #[ink(message)]
+        pub fn created_at_block(&self) -> BlockNumber {
+            self.created_at_block
+        }
+
+        /// Returns how long the raffle has been live.
+        #[ink(message)]
+        pub fn elapsed_since_start(&self) -> Timestamp {
+            self.env().block_timestamp() - self.created_at
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{call, test, DefaultEnvironment};
+        use ink_lang as ink;
+
+        fn accounts() -> test::DefaultAccounts<DefaultEnvironment> {
+            test::default_accounts::<DefaultEnvironment>().expect("default accounts")
+        }
+
+        fn contract_id() -> AccountId {
+            AccountId::from([0x07; 32])
+        }
+
+        fn balance_of(who: AccountId) -> Balance {
+            test::get_account_balance::<DefaultEnvironment>(who).unwrap_or(0)
+        }
+
+        /// Whether running `f` reaches a cross-contract call, which the off-chain
+        /// environment can't make and panics on.
+        fn calls_out<F: FnOnce()>(f: F) -> bool {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).is_err()
+        }
+
+        /// Makes `caller` call the next message with `value`, crediting `value`
+        /// to the contract as the chain would.
+        fn set_caller(caller: AccountId, value: Balance) {
+            test::pop_execution_context();
+            test::push_execution_context::<DefaultEnvironment>(
+                caller,
+                contract_id(),
+                1_000_000,
+                value,
+                test::CallData::new(call::Selector::new([0x00; 4])),
+            );
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), balance_of(contract_id()) + value)
+                .expect("contract account");
+        }
+
+        fn now() -> Timestamp {
+            ink_env::block_timestamp::<DefaultEnvironment>().expect("block timestamp")
+        }
+
+        /// Advances blocks until the block timestamp reaches `at`.
+        fn advance_to(at: Timestamp) {
+            while now() < at {
+                test::advance_block::<DefaultEnvironment>().expect("advance block");
+            }
+        }
+
+        fn advance_by(duration: Timestamp) {
+            advance_to(now() + duration);
+        }
+
+        /// A raffle deployed by alice for the beneficiary eve.
+        fn new_raffle() -> Raffle {
+            set_caller(accounts().alice, 0);
+            let raffle = Raffle::new(accounts().eve);
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), raffle.reserve()).expect("contract account");
+            raffle
+        }
+
+        /// A player account distinct from the default accounts.
+        fn player(n: u8) -> AccountId {
+            AccountId::from([0x10 + n; 32])
+        }
+
+        fn play(raffle: &mut Raffle, who: AccountId, balance: Balance) -> Result<()> {
+            set_caller(who, balance);
+            raffle.play()
+        }
+
+        /// Enters `count` players with the minimum deposit.
+        fn fill(raffle: &mut Raffle, count: u8) {
+            for n in 0..count {
+                play(raffle, player(n), MIN_DEPOSIT).expect("play");
+            }
+        }
+
+        /// Enters the minimum player count and opens the draw window.
+        fn ready_to_draw(raffle: &mut Raffle) {
+            fill(raffle, MINI_PLAYER_COUNT as u8);
+            advance_to(raffle.draw_starts_at);
+        }
+
+        /// Draws until the raffle finishes.
+        fn draw_to_finish(raffle: &mut Raffle) {
+            while !raffle.finished() {
+                raffle.draw(None).expect("draw");
+            }
+        }
+
+        fn as_owner() {
+            set_caller(accounts().alice, 0);
+        }
+
+        /// Sets the block entropy so `random()` on the next draw's seed returns all zeros.
+        fn zero_randomness(raffle: &Raffle) {
+            // the off-chain `random()` XORs the entropy with the subject and its indices.
+            let mut entropy = [0u8; 32];
+            for (i, b) in raffle.draw_seed().iter().enumerate() {
+                entropy[i] = b ^ i as u8;
+            }
+            test::set_block_entropy::<DefaultEnvironment>(Hash::from(entropy)).expect("block entropy");
+        }
+
+        type Event = <Raffle as ::ink_lang::BaseEvent>::Type;
+
+        fn events() -> Vec<Event> {
+            test::recorded_events()
+                .map(|event| <Event as scale::Decode>::decode(&mut &event.data[..]).expect("event"))
+                .collect()
+        }
+
+        /// Withdraws the payout and starts the next round.
+        fn settle_and_reset(raffle: &mut Raffle) {
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().expect("withdraw");
+            as_owner();
+            raffle.reset().expect("reset");
+        }
+
+        /// A raffle like `new_raffle` whose entries vote between bob, charlie and django.
+        fn charity_raffle() -> Raffle {
+            set_caller(accounts().alice, 0);
+            let charities = vec![accounts().bob, accounts().charlie, accounts().django];
+            let raffle = Raffle::with_charities(accounts().eve, charities);
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), raffle.reserve()).expect("contract account");
+            raffle
+        }
+
+        #[ink::test]
+        fn elapsed_since_start_counts_from_deployment() {
+            let raffle = new_raffle();
+            assert_eq!(raffle.elapsed_since_start(), 0);
+            advance_by(1000);
+            assert_eq!(raffle.elapsed_since_start(), 1000);
+        }
+
+        #[ink::test]
+        fn token_deposits_and_native_donations_are_paid_out_apart() {
+            let mut native = new_raffle();
+            set_caller(player(0), 0);
+            assert_eq!(native.play_with_token(MIN_DEPOSIT), Err(Error::NoEntryToken));
+
+            set_caller(accounts().alice, 0);
+            let token = AccountId::from([0x22; 32]);
+            let mut raffle = Raffle::with_entry_token(accounts().eve, token);
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::EntryTokenRequired));
+            set_caller(player(0), 0);
+            assert_eq!(raffle.play_with_token(MIN_DEPOSIT - 1), Err(Error::InvalidDepositAmount));
+            assert!(calls_out(|| {
+                let _ = raffle.play_with_token(MIN_DEPOSIT);
+            }));
+
+            // the off-chain environment can't move tokens, so the token entries are recorded directly.
+            for n in 0..MINI_PLAYER_COUNT as u8 {
+                raffle.enter(player(n), MIN_DEPOSIT).unwrap();
+            }
+            set_caller(accounts().bob, 3 * MIN_DEPOSIT);
+            assert_eq!(raffle.donate(), Ok(()));
+
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.pending_withdrawal(), 3 * MIN_DEPOSIT);
+            assert_eq!(raffle.pending_token_withdrawal(), 5 * MIN_DEPOSIT);
+
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.withdraw(), Ok(()));
+            assert_eq!(balance_of(accounts().eve), 3 * MIN_DEPOSIT);
+            assert!(calls_out(|| {
+                let _ = raffle.withdraw_tokens();
+            }));
+            assert_eq!(native.withdraw_tokens(), Err(Error::NoEntryToken));
+        }
+
+        #[ink::test]
+        fn pushed_token_payouts_follow_the_native_payout() {
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), raffle.reserve()).expect("contract account");
+            raffle.set_push_payout(true).unwrap();
+            for n in 0..MINI_PLAYER_COUNT as u8 {
+                raffle.enter(player(n), MIN_DEPOSIT).unwrap();
+            }
+            set_caller(accounts().bob, 3 * MIN_DEPOSIT);
+            raffle.donate().unwrap();
+
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw(None), Ok(()));
+            // the donations are sent natively before the deposits are sent in the token.
+            assert!(calls_out(|| {
+                let _ = raffle.draw(None);
+            }));
+            assert_eq!(balance_of(accounts().eve), 3 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn donations_are_paid_out_with_the_deposits() {
+            let mut raffle = new_raffle();
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.donate(), Err(Error::InvalidDonationAmount));
+            set_caller(accounts().bob, 3 * MIN_DEPOSIT);
+            assert_eq!(raffle.donate(), Ok(()));
+
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            let payout = 5 * MIN_DEPOSIT + 3 * MIN_DEPOSIT;
+            assert_eq!(raffle.pending_withdrawal(), payout);
+
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.withdraw(), Ok(()));
+            assert_eq!(balance_of(accounts().eve), payout);
+            assert_eq!(raffle.donate(), Err(Error::Finished));
+        }
+
+        #[ink::test]
+        fn frozen_entries_are_rejected_once_the_draw_window_opens() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_freeze_entries_at_draw_time(true).unwrap();
+            fill(&mut raffle, 5);
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT), Ok(()));
+
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(play(&mut raffle, player(6), MIN_DEPOSIT), Err(Error::EntriesFrozen));
+
+            as_owner();
+            raffle.set_freeze_entries_at_draw_time(false).unwrap();
+            assert_eq!(play(&mut raffle, player(6), MIN_DEPOSIT), Ok(()));
+        }
+
+        #[ink::test]
+        fn getters_track_beneficiary_and_owner_changes() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.get_beneficiary(), accounts().eve);
+            assert_eq!(raffle.get_owner(), accounts().alice);
+
+            as_owner();
+            raffle.set_beneficiary(accounts().frank).unwrap();
+            raffle.transfer_ownership(accounts().bob).unwrap();
+            assert_eq!(raffle.get_beneficiary(), accounts().eve);
+            assert_eq!(raffle.get_owner(), accounts().alice);
+
+            set_caller(accounts().frank, 0);
+            raffle.accept_beneficiary().unwrap();
+            set_caller(accounts().bob, 0);
+            raffle.accept_ownership().unwrap();
+            assert_eq!(raffle.get_beneficiary(), accounts().frank);
+            assert_eq!(raffle.get_owner(), accounts().bob);
+        }
+
+        #[ink::test]
+        fn strict_randomness_rejects_zeroed_output() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_strict_randomness(true).unwrap();
+            ready_to_draw(&mut raffle);
+
+            zero_randomness(&raffle);
+            assert_eq!(raffle.draw(None), Err(Error::RandomnessUnavailable));
+            assert!(raffle.winners.is_empty());
+            assert!(!raffle.used_fallback());
+        }
+
+        #[ink::test]
+        fn zeroed_randomness_falls_back_to_the_block_seed() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert!(!raffle.used_fallback());
+
+            zero_randomness(&raffle);
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert_eq!(raffle.winners.len(), 1);
+            assert!(raffle.used_fallback());
+        }
+
+        #[ink::test]
+        fn stats_reflect_plays_donations_and_draws() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 5);
+            play(&mut raffle, player(5), 2 * MIN_DEPOSIT).unwrap();
+            set_caller(accounts().bob, MIN_DEPOSIT);
+            raffle.donate().unwrap();
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+
+            assert_eq!(
+                raffle.stats(),
+                RaffleStats {
+                    unique_participants: 6,
+                    total_tickets: 7,
+                    total_pot: 7 * MIN_DEPOSIT,
+                    total_donations: MIN_DEPOSIT,
+                    winners_drawn: 1,
+                    rounds_completed: 0,
+                }
+            );
+
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.stats().rounds_completed, 1);
+        }
+
+        #[ink::test]
+        fn draw_seed_differs_by_contract_and_round() {
+            let mut raffle = new_raffle();
+            let seed = raffle.draw_seed();
+            assert_eq!(raffle.draw_seed(), seed);
+
+            raffle.round = 1;
+            assert_ne!(raffle.draw_seed(), seed);
+            raffle.round = 0;
+
+            // the same raffle state under another contract id, at the same timestamp.
+            test::push_execution_context::<DefaultEnvironment>(
+                accounts().alice,
+                AccountId::from([0x08; 32]),
+                1_000_000,
+                0,
+                test::CallData::new(call::Selector::new([0x00; 4])),
+            );
+            assert_ne!(raffle.draw_seed(), seed);
+        }
+
+        #[ink::test]
+        fn increase_deposit_tops_up_an_open_entry() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::HasNotPlayed));
+
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            as_owner();
+            raffle.set_weighting(Weighting::Tickets).unwrap();
+            assert_eq!(raffle.weight_of(player(0)), 1);
+
+            set_caller(player(0), 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Ok(()));
+            assert_eq!(raffle.participants.get(&player(0)), Some(&(3 * MIN_DEPOSIT)));
+            assert_eq!(raffle.weight_of(player(0)), 3);
+
+            set_caller(player(0), MAX_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::InvalidDepositAmount));
+            set_caller(player(0), 0);
+            assert_eq!(raffle.increase_deposit(), Err(Error::InvalidDepositAmount));
+            assert_eq!(raffle.participants.get(&player(0)), Some(&(3 * MIN_DEPOSIT)));
+
+            // frozen entries can't be topped up either.
+            as_owner();
+            raffle.set_freeze_entries_at_draw_time(true).unwrap();
+            for n in 1..MINI_PLAYER_COUNT as u8 {
+                play(&mut raffle, player(n), MIN_DEPOSIT).unwrap();
+            }
+            advance_to(raffle.draw_starts_at);
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::EntriesFrozen));
+
+            // a token raffle only takes deposits in its token.
+            as_owner();
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            raffle.enter(player(0), MIN_DEPOSIT).unwrap();
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::EntryTokenRequired));
+        }
+
+        fn blake2x256<T: scale::Encode>(value: &T) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            ink_env::hash_encoded::<Blake2x256, _>(value, &mut output);
+            output
+        }
+
+        fn merkle_parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+            if a <= b {
+                blake2x256(&(a, b))
+            } else {
+                blake2x256(&(b, a))
+            }
+        }
+
+        #[ink::test]
+        fn verify_winner_checks_proofs_against_the_root() {
+            let mut raffle = new_raffle();
+            let (bob, charlie, django) = (accounts().bob, accounts().charlie, accounts().django);
+            assert!(!raffle.verify_winner(bob, Vec::new()));
+
+            let leaves = [blake2x256(&bob), blake2x256(&charlie), blake2x256(&django)];
+            let left = merkle_parent(leaves[0], leaves[1]);
+            let root = merkle_parent(left, leaves[2]);
+
+            set_caller(bob, 0);
+            assert_eq!(raffle.publish_winner_root(root), Err(Error::NotOwner));
+            as_owner();
+            raffle.publish_winner_root(root).unwrap();
+
+            assert!(raffle.verify_winner(bob, vec![leaves[1], leaves[2]]));
+            assert!(raffle.verify_winner(charlie, vec![leaves[0], leaves[2]]));
+            assert!(raffle.verify_winner(django, vec![left]));
+            assert!(!raffle.verify_winner(django, vec![leaves[0]]));
+            assert!(!raffle.verify_winner(accounts().frank, vec![leaves[1], leaves[2]]));
+        }
+
+        #[ink::test]
+        fn reentry_waits_for_the_cooldown() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_reentry_cooldown(1000).unwrap();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.withdraw_entry(), Ok(()));
+            assert_eq!(balance_of(player(0)), MIN_DEPOSIT);
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::ReentryCooldown));
+
+            advance_by(1000);
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Ok(()));
+        }
+
+        #[ink::test]
+        fn reentry_cooldown_saturates() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_reentry_cooldown(Timestamp::MAX).unwrap();
+            advance_by(5);
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            set_caller(player(0), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::ReentryCooldown));
+        }
+
+        #[ink::test]
+        fn token_entries_are_withdrawn_in_the_token() {
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            raffle.enter(player(0), MIN_DEPOSIT).unwrap();
+            set_caller(player(0), 0);
+            // the refund goes out as a token transfer, which the off-chain environment can't make.
+            assert!(calls_out(|| {
+                let _ = raffle.withdraw_entry();
+            }));
+        }
+
+        #[ink::test]
+        fn defaults_match_the_constants() {
+            let raffle = new_raffle();
+            assert_eq!(raffle.defaults(), (DRAW_COUNTDOWN, MINI_PLAYER_COUNT));
+        }
+
+        #[ink::test]
+        fn is_full_once_the_cap_is_reached() {
+            let mut raffle = new_raffle();
+            assert!(!raffle.is_full());
+            as_owner();
+            raffle.set_max_participants(Some(2)).unwrap();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            assert!(!raffle.is_full());
+            assert_eq!(raffle.slots_remaining(), Some(1));
+
+            play(&mut raffle, player(1), MIN_DEPOSIT).unwrap();
+            assert!(raffle.is_full());
+            assert_eq!(play(&mut raffle, player(2), MIN_DEPOSIT), Err(Error::RaffleFull));
+        }
+
+        #[ink::test]
+        fn registry_is_called_when_the_raffle_finishes() {
+            let mut raffle = new_raffle();
+            as_owner();
+            assert_eq!(raffle.set_registry(Some(accounts().django)), Ok(()));
+            ready_to_draw(&mut raffle);
+            assert!(!calls_out(|| raffle.draw(None).unwrap()));
+            assert!(calls_out(|| raffle.draw(None).unwrap()));
+        }
+
+        #[ink::test]
+        fn set_registry_is_owner_only() {
+            let mut raffle = new_raffle();
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.set_registry(Some(accounts().django)), Err(Error::NotOwner));
+            assert_eq!(raffle.registry, None);
+        }
+
+        #[ink::test]
+        fn odds_distribution_ignores_deposit_sizes() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 4);
+            play(&mut raffle, player(4), 6 * MIN_DEPOSIT).unwrap();
+
+            let odds = raffle.odds_distribution();
+            let expected: Vec<(AccountId, u32)> = (0..5).map(|n| (player(n), 20)).collect();
+            assert_eq!(odds, expected);
+        }
+
+        #[ink::test]
+        fn odds_distribution_follows_skewed_weights() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 4);
+            play(&mut raffle, player(4), 6 * MIN_DEPOSIT).unwrap();
+            as_owner();
+            raffle.set_weighting(Weighting::Tickets).unwrap();
+
+            let odds = raffle.odds_distribution();
+            let expected: Vec<(AccountId, u32)> = (0..5).map(|n| (player(n), if n == 4 { 60 } else { 10 })).collect();
+            assert_eq!(odds, expected);
+        }
+
+        #[ink::test]
+        fn odds_distribution_gives_leftover_points_to_the_earliest() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 3);
+            let odds: Vec<u32> = raffle.odds_distribution().into_iter().map(|(_, share)| share).collect();
+            assert_eq!(odds, vec![34, 33, 33]);
+        }
+
+        #[ink::test]
+        fn paused_draws_still_take_entries() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.pause_draw(), Err(Error::NotOwner));
+            as_owner();
+            raffle.pause_draw().unwrap();
+
+            assert_eq!(raffle.draw(None), Err(Error::DrawPaused));
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT), Ok(()));
+
+            as_owner();
+            raffle.resume_draw().unwrap();
+            assert_eq!(raffle.draw(None), Ok(()));
+        }
+
+        fn assert_candidate_set_in_sync(raffle: &Raffle) {
+            let mut candidates: Vec<AccountId> = raffle.candidates.iter().copied().collect();
+            let mut set: Vec<AccountId> = raffle.candidate_set.keys().copied().collect();
+            candidates.sort();
+            set.sort();
+            assert_eq!(candidates, set);
+        }
+
+        #[ink::test]
+        fn candidate_set_stays_in_sync_through_withdrawals_and_draws() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 8);
+            set_caller(player(3), 0);
+            raffle.withdraw_entry().unwrap();
+            assert!(!raffle.is_candidate(player(3)));
+            assert!(raffle.is_candidate(player(4)));
+            assert_candidate_set_in_sync(&raffle);
+
+            advance_to(raffle.draw_starts_at);
+            while !raffle.finished() {
+                raffle.draw(None).unwrap();
+                assert_candidate_set_in_sync(&raffle);
+                let (_, last) = raffle.winners();
+                assert!(!raffle.is_candidate(last.unwrap()));
+            }
+        }
+
+        #[ink::test]
+        fn deposit_range_spans_the_deposits() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.deposit_range(), None);
+            play(&mut raffle, player(0), 3 * MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.deposit_range(), Some((3 * MIN_DEPOSIT, 3 * MIN_DEPOSIT)));
+            play(&mut raffle, player(1), MAX_DEPOSIT).unwrap();
+            play(&mut raffle, player(2), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.deposit_range(), Some((MIN_DEPOSIT, MAX_DEPOSIT)));
+        }
+
+        #[ink::test]
+        fn quiet_raffles_skip_played_but_not_finished() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_emit_events(false).unwrap();
+            ready_to_draw(&mut raffle);
+            set_caller(accounts().bob, MIN_DEPOSIT);
+            raffle.donate().unwrap();
+            draw_to_finish(&mut raffle);
+
+            let events = events();
+            assert!(!events.iter().any(|e| matches!(e, Event::Played(_) | Event::Donated(_))));
+            assert!(events.iter().any(|e| matches!(e, Event::ThresholdReached(_))));
+            assert_eq!(events.iter().filter(|e| matches!(e, Event::Draw(_))).count(), 2);
+            assert!(events.iter().any(|e| matches!(e, Event::Finished(_))));
+        }
+
+        #[ink::test]
+        fn played_is_emitted_by_default() {
+            let mut raffle = new_raffle();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            assert!(matches!(
+                events().last(),
+                Some(Event::Played(Played { who, balance })) if *who == player(0) && *balance == MIN_DEPOSIT
+            ));
+        }
+
+        #[ink::test]
+        fn new_raffles_report_the_current_storage_version() {
+            let raffle = new_raffle();
+            assert_eq!(raffle.storage_version(), STORAGE_VERSION);
+        }
+
+        #[ink::test]
+        fn draw_status_follows_the_round() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.draw_status(), DrawStatus::NotEnoughPlayers { needed: 5 });
+            fill(&mut raffle, 3);
+            assert_eq!(raffle.draw_status(), DrawStatus::NotEnoughPlayers { needed: 2 });
+
+            play(&mut raffle, player(3), MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(4), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.draw_status(), DrawStatus::InCountdown { remaining: DRAW_COUNTDOWN });
+            advance_by(100);
+            assert_eq!(raffle.draw_status(), DrawStatus::InCountdown { remaining: DRAW_COUNTDOWN - 100 });
+
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw_status(), DrawStatus::Ready);
+            as_owner();
+            raffle.pause_draw().unwrap();
+            assert_eq!(raffle.draw_status(), DrawStatus::Paused);
+            raffle.resume_draw().unwrap();
+
+            raffle.set_manual_close(true).unwrap();
+            assert_eq!(raffle.draw_status(), DrawStatus::Blocked { reason: Error::EntriesOpen });
+            raffle.close_entries().unwrap();
+            assert_eq!(raffle.draw_status(), DrawStatus::Ready);
+
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.draw_status(), DrawStatus::Finished);
+        }
+
+        #[ink::test]
+        fn draw_status_reports_refunds_and_blocked_draws() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.commit_seed(raffle.hash_seed([7; 32])).unwrap();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.draw_status(), DrawStatus::Blocked { reason: Error::SeedNotRevealed });
+
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 2);
+            as_owner();
+            raffle.cancel().unwrap();
+            assert_eq!(raffle.draw_status(), DrawStatus::Refunding);
+        }
+
+        #[ink::test]
+        fn auto_draw_draws_one_winner_per_interaction() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_auto_draw(true).unwrap();
+            fill(&mut raffle, 4);
+            assert!(raffle.winners.is_empty());
+
+            // the entry reaching the threshold only starts the countdown.
+            play(&mut raffle, player(4), MIN_DEPOSIT).unwrap();
+            assert!(raffle.winners.is_empty());
+
+            advance_to(raffle.draw_starts_at);
+            play(&mut raffle, player(5), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.winners.len(), 1);
+            assert_eq!(raffle.participants.len(), 6);
+
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.poke(), Ok(()));
+            assert_eq!(raffle.winners.len(), 2);
+            assert!(raffle.finished());
+        }
+
+        #[ink::test]
+        fn poke_does_nothing_without_auto_draw() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.poke(), Ok(()));
+            assert!(raffle.winners.is_empty());
+        }
+
+        #[ink::test]
+        fn ownership_transfer_is_accepted_by_the_pending_owner() {
+            let mut raffle = new_raffle();
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.transfer_ownership(accounts().bob), Err(Error::NotOwner));
+            as_owner();
+            raffle.transfer_ownership(accounts().bob).unwrap();
+            assert_eq!(raffle.pending_owner(), Some(accounts().bob));
+
+            set_caller(accounts().charlie, 0);
+            assert_eq!(raffle.accept_ownership(), Err(Error::NotPendingOwner));
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.accept_ownership(), Ok(()));
+            assert_eq!(raffle.get_owner(), accounts().bob);
+            assert_eq!(raffle.pending_owner(), None);
+            assert!(matches!(
+                events().last(),
+                Some(Event::OwnershipTransferred(OwnershipTransferred { previous_owner, new_owner }))
+                    if *previous_owner == accounts().alice && *new_owner == accounts().bob
+            ));
+        }
+
+        #[ink::test]
+        fn ownership_transfer_can_be_cancelled() {
+            let mut raffle = new_raffle();
+            as_owner();
+            assert_eq!(raffle.cancel_ownership_transfer(), Err(Error::NoPendingOwner));
+            raffle.transfer_ownership(accounts().bob).unwrap();
+            assert_eq!(raffle.cancel_ownership_transfer(), Ok(()));
+            assert!(matches!(events().last(), Some(Event::OwnershipTransferCancelled(_))));
+
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.accept_ownership(), Err(Error::NotPendingOwner));
+            assert_eq!(raffle.get_owner(), accounts().alice);
+        }
+
+        #[ink::test]
+        fn next_winner_ordinal_counts_the_draws() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.next_winner_ordinal(), Some(0));
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.next_winner_ordinal(), Some(1));
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.next_winner_ordinal(), None);
+        }
+
+        #[ink::test]
+        fn pushed_payout_reaches_the_beneficiary_at_finish() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_push_payout(true).unwrap();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+
+            assert_eq!(balance_of(accounts().eve), 5 * MIN_DEPOSIT);
+            assert_eq!(raffle.pending_withdrawal(), 0);
+            assert_eq!(balance_of(contract_id()), raffle.reserve());
+        }
+
+        #[ink::test]
+        fn pulled_payout_waits_for_withdraw() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            assert_eq!(balance_of(accounts().eve), 0);
+            assert_eq!(raffle.pending_withdrawal(), 5 * MIN_DEPOSIT);
+
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.withdraw(), Err(Error::NotBeneficiary));
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.withdraw(), Ok(()));
+            assert_eq!(balance_of(accounts().eve), 5 * MIN_DEPOSIT);
+            assert_eq!(raffle.withdraw(), Err(Error::NothingToWithdraw));
+        }
+
+        #[ink::test]
+        fn hash_seed_commitment_is_accepted_by_reveal_seed() {
+            let mut raffle = new_raffle();
+            let seed = [7u8; 32];
+            as_owner();
+            assert_eq!(raffle.reveal_seed(seed), Err(Error::NoSeedCommitted));
+            raffle.commit_seed(raffle.hash_seed(seed)).unwrap();
+            assert_eq!(raffle.commit_seed(raffle.hash_seed(seed)), Err(Error::SeedAlreadyCommitted));
+
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.draw(None), Err(Error::SeedNotRevealed));
+            assert_eq!(raffle.reveal_seed([8u8; 32]), Err(Error::InvalidSeed));
+            assert_eq!(raffle.reveal_seed(seed), Ok(()));
+            assert_eq!(raffle.draw(None), Ok(()));
+        }
+
+        #[ink::test]
+        fn seeds_cannot_be_committed_once_the_window_opens() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            as_owner();
+            assert_eq!(raffle.commit_seed(raffle.hash_seed([7u8; 32])), Err(Error::CommitClosed));
+        }
+
+        #[ink::test]
+        fn play_with_name_stores_a_checked_name() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.play_with_name(b"Ada".to_vec()), Ok(()));
+            assert_eq!(raffle.name_of(player(0)), Some(b"Ada".to_vec()));
+
+            set_caller(player(1), MIN_DEPOSIT);
+            assert_eq!(raffle.play_with_name(vec![b'a'; MAX_NAME_LEN + 1]), Err(Error::NameTooLong));
+            assert_eq!(raffle.play_with_name(vec![0xFF, 0xFE]), Err(Error::InvalidName));
+            assert_eq!(raffle.name_of(player(1)), None);
+            assert!(!raffle.participants.contains_key(&player(1)));
+
+            assert_eq!(play(&mut raffle, player(1), MIN_DEPOSIT), Ok(()));
+            assert_eq!(raffle.name_of(player(1)), None);
+        }
+
+        #[ink::test]
+        fn draw_precheck_matches_draw() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 5);
+            assert_eq!(raffle.draw_precheck(), Err(Error::DrawNotStarted));
+            assert_eq!(raffle.draw(None), Err(Error::DrawNotStarted));
+
+            set_caller(player(0), 0);
+            raffle.withdraw_entry().unwrap();
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw_precheck(), Err(Error::NotEnoughPlayer));
+            assert_eq!(raffle.draw(None), Err(Error::NotEnoughPlayer));
+
+            // reaching the threshold again restarts the countdown.
+            play(&mut raffle, player(5), MIN_DEPOSIT).unwrap();
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw_precheck(), Ok(()));
+            assert!(raffle.winners.is_empty());
+            assert_eq!(raffle.draw(None), Ok(()));
+        }
+
+        fn candidate_order(raffle: &Raffle) -> Vec<AccountId> {
+            raffle.candidates.iter().copied().collect()
+        }
+
+        #[ink::test]
+        fn stable_removal_keeps_the_entry_order() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 5);
+            raffle.remove_candidate_at(1);
+            assert_eq!(candidate_order(&raffle), vec![player(0), player(4), player(2), player(3)]);
+
+            as_owner();
+            raffle.set_stable_removal(true).unwrap();
+            raffle.remove_candidate_at(1);
+            assert_eq!(candidate_order(&raffle), vec![player(0), player(2), player(3)]);
+            assert!(!raffle.is_candidate(player(4)));
+        }
+
+        #[ink::test]
+        fn stable_removal_keeps_the_order_across_draws() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_stable_removal(true).unwrap();
+            fill(&mut raffle, 8);
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+
+            let expected: Vec<AccountId> = (0..8).map(player).filter(|p| !raffle.winners.iter().any(|w| w == p)).collect();
+            assert_eq!(candidate_order(&raffle), expected);
+        }
+
+        #[ink::test]
+        fn can_i_play_reports_the_blocking_precondition() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), 0);
+            assert_eq!(raffle.can_i_play(), Ok(()));
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            set_caller(player(0), 0);
+            assert_eq!(raffle.can_i_play(), Err(Error::HasPlayed));
+
+            as_owner();
+            raffle.set_reentry_cooldown(1000).unwrap();
+            play(&mut raffle, player(1), MIN_DEPOSIT).unwrap();
+            set_caller(player(1), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(raffle.can_i_play(), Err(Error::ReentryCooldown));
+
+            as_owner();
+            raffle.set_max_participants(Some(1)).unwrap();
+            set_caller(player(2), 0);
+            assert_eq!(raffle.can_i_play(), Err(Error::RaffleFull));
+
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_freeze_entries_at_draw_time(true).unwrap();
+            fill(&mut raffle, 5);
+            advance_to(raffle.draw_starts_at);
+            set_caller(player(5), 0);
+            assert_eq!(raffle.can_i_play(), Err(Error::EntriesFrozen));
+        }
+
+        #[ink::test]
+        fn paused_draws_leave_can_i_play_open() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.pause_draw().unwrap();
+            set_caller(player(0), 0);
+            assert_eq!(raffle.can_i_play(), Ok(()));
+        }
+
+        #[ink::test]
+        fn draws_fetch_randomness_from_the_oracle() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.set_randomness_oracle(Some(accounts().django)), Err(Error::NotOwner));
+
+            as_owner();
+            raffle.set_randomness_oracle(Some(accounts().django)).unwrap();
+            assert!(calls_out(|| {
+                let _ = raffle.draw(None);
+            }));
+
+            as_owner();
+            raffle.set_randomness_oracle(None).unwrap();
+            assert!(!calls_out(|| raffle.draw(None).unwrap()));
+        }
+
+        #[ink::test]
+        fn lifetime_donated_sums_every_round() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            settle_and_reset(&mut raffle);
+            assert_eq!(raffle.total_donated_lifetime(), 5 * MIN_DEPOSIT);
+
+            fill(&mut raffle, 6);
+            set_caller(accounts().bob, MIN_DEPOSIT);
+            raffle.donate().unwrap();
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            settle_and_reset(&mut raffle);
+            assert_eq!(raffle.total_donated_lifetime(), 12 * MIN_DEPOSIT);
+            assert_eq!(raffle.round(), 2);
+        }
+
+        #[ink::test]
+        fn drawn_out_candidates_and_missing_players_are_told_apart() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            set_caller(player(0), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(raffle.draw(None), Err(Error::NotEnoughPlayer));
+
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            advance_to(raffle.draw_starts_at);
+            // every candidate drawn out while the round is still open.
+            for n in 0..5 {
+                raffle.candidate_set.take(&player(n));
+            }
+            raffle.candidates.clear();
+            assert_eq!(raffle.draw(None), Err(Error::NoCandidates));
+            assert_eq!(raffle.draw_precheck(), Err(Error::NoCandidates));
+        }
+
+        #[ink::test]
+        fn rescue_token_is_owner_only_and_calls_the_token() {
+            let mut raffle = new_raffle();
+            let token = accounts().django;
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.rescue_token(token, accounts().bob, 1), Err(Error::NotOwner));
+
+            as_owner();
+            assert!(calls_out(|| {
+                let _ = raffle.rescue_token(token, accounts().bob, 1);
+            }));
+        }
+
+        #[ink::test]
+        fn rescue_token_rejects_the_entry_token() {
+            let entry_token = AccountId::from([0x22; 32]);
+            let mut raffle = Raffle::with_entry_token(accounts().eve, entry_token);
+            assert_eq!(
+                raffle.rescue_token(entry_token, accounts().bob, 1),
+                Err(Error::CannotRescueEntryToken)
+            );
+            assert!(calls_out(|| {
+                let _ = raffle.rescue_token(accounts().django, accounts().bob, 1);
+            }));
+        }
+
+        #[ink::test]
+        fn psp22_results_decode_like_the_standard() {
+            type TransferResult = core::result::Result<(), Psp22Error>;
+            let decode = |bytes: &[u8]| <TransferResult as scale::Decode>::decode(&mut &bytes[..]);
+            assert_eq!(decode(&[0]), Ok(Ok(())));
+            assert_eq!(decode(&[1, 1]), Ok(Err(Psp22Error::InsufficientBalance)));
+            assert_eq!(decode(&[1, 0, 4, 0xAB]), Ok(Err(Psp22Error::Custom(vec![0xAB]))));
+        }
+
+        #[ink::test]
+        fn top_donors_are_never_drawn() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 5);
+            play(&mut raffle, player(5), 3 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(6), 3 * MIN_DEPOSIT).unwrap();
+            as_owner();
+            raffle.set_exclude_top_donor_from_winning(true).unwrap();
+
+            let odds = raffle.odds_distribution();
+            assert!(odds.iter().all(|(who, share)| (*share == 0) == (*who == player(5) || *who == player(6))));
+
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.winners.len(), 2);
+            assert!(!raffle.winners.iter().any(|w| *w == player(5) || *w == player(6)));
+            assert_eq!(raffle.pending_withdrawal(), 11 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn top_donor_exclusion_needs_someone_left_to_win() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 5);
+            as_owner();
+            assert_eq!(raffle.set_exclude_top_donor_from_winning(true), Err(Error::NoEligibleCandidates));
+            play(&mut raffle, player(5), 2 * MIN_DEPOSIT).unwrap();
+            as_owner();
+            assert_eq!(raffle.set_exclude_top_donor_from_winning(true), Ok(()));
+        }
+
+        #[ink::test]
+        fn winners_page_pages_in_draw_order() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            let winners: Vec<AccountId> = raffle.winners.iter().copied().collect();
+
+            assert_eq!(raffle.winners_page(0, 1), vec![winners[0]]);
+            assert_eq!(raffle.winners_page(1, 1), vec![winners[1]]);
+            assert_eq!(raffle.winners_page(0, 10), winners);
+            assert!(raffle.winners_page(2, 1).is_empty());
+        }
+
+        #[ink::test]
+        fn projected_finish_becomes_the_recorded_finish() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.projected_finish(), None);
+            as_owner();
+            raffle.set_draw_gap(1000).unwrap();
+            fill(&mut raffle, 5);
+            assert_eq!(raffle.projected_finish(), Some(raffle.draw_starts_at + 1000));
+
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.projected_finish(), Some(now() + 1000));
+
+            advance_by(1500);
+            let finished_at = now();
+            raffle.draw(None).unwrap();
+            assert!(raffle.finished());
+            advance_by(500);
+            assert_eq!(raffle.projected_finish(), Some(finished_at));
+        }
+
+        #[ink::test]
+        fn reset_waits_for_the_payout() {
+            let mut raffle = new_raffle();
+            as_owner();
+            assert_eq!(raffle.reset(), Err(Error::NotFinished));
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+
+            as_owner();
+            assert_eq!(raffle.reset(), Err(Error::PayoutPending));
+            assert_eq!(raffle.round(), 0);
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            as_owner();
+            assert_eq!(raffle.reset(), Ok(()));
+            assert_eq!(raffle.round(), 1);
+        }
+
+        #[ink::test]
+        fn reset_waits_for_the_token_payout() {
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            for n in 0..MINI_PLAYER_COUNT as u8 {
+                raffle.enter(player(n), MIN_DEPOSIT).unwrap();
+            }
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+
+            assert_eq!(raffle.pending_withdrawal(), 0);
+            assert!(!raffle.payout_complete());
+            as_owner();
+            assert_eq!(raffle.reset(), Err(Error::PayoutPending));
+        }
+
+        #[ink::test]
+        fn raffle_finishes_early_once_no_one_can_win() {
+            let mut raffle = new_raffle();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            for n in 1..5 {
+                play(&mut raffle, player(n), 2 * MIN_DEPOSIT).unwrap();
+            }
+            as_owner();
+            raffle.set_exclude_top_donor_from_winning(true).unwrap();
+            assert_eq!(raffle.distinct_candidate_accounts(), 5);
+
+            // every candidate left after the only eligible one wins is a top donor.
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            assert!(raffle.finished());
+            assert_eq!(raffle.winners.len(), 1);
+            assert_eq!(raffle.winners.first(), Some(&player(0)));
+            assert_eq!(raffle.pending_withdrawal(), 9 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn raffle_finishes_early_once_candidates_run_out() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            // all but one candidate gone while the round is open.
+            while raffle.candidates.len() > 1 {
+                raffle.remove_candidate_at(0);
+            }
+            assert_eq!(raffle.distinct_candidate_accounts(), 1);
+            raffle.draw(None).unwrap();
+            assert!(raffle.finished());
+            assert_eq!(raffle.winners.len(), 1);
+        }
+
+        #[ink::test]
+        fn empty_pot_finishes_without_a_transfer() {
+            // a token raffle without donations has nothing to pay out natively.
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            raffle.set_push_payout(true).unwrap();
+            for n in 0..MINI_PLAYER_COUNT as u8 {
+                raffle.enter(player(n), MIN_DEPOSIT).unwrap();
+            }
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            assert!(calls_out(|| {
+                let _ = raffle.draw(None);
+            }));
+            assert!(matches!(
+                events().last(),
+                Some(Event::Finished(Finished { beneficiary, balance })) if *beneficiary == accounts().eve && *balance == 0
+            ));
+            assert_eq!(balance_of(accounts().eve), 0);
+            assert_eq!(raffle.pending_withdrawal(), 0);
+        }
+
+        #[ink::test]
+        fn every_entry_moves_the_accumulator_and_the_seed() {
+            let mut raffle = new_raffle();
+            let mut seen = vec![raffle.entropy_accumulator()];
+            let mut seeds = vec![raffle.draw_seed()];
+            for n in 0..3 {
+                play(&mut raffle, player(n), MIN_DEPOSIT).unwrap();
+                assert!(!seen.contains(&raffle.entropy_accumulator()));
+                assert!(!seeds.contains(&raffle.draw_seed()));
+                seen.push(raffle.entropy_accumulator());
+                seeds.push(raffle.draw_seed());
+            }
+        }
+
+        #[ink::test]
+        fn fallback_seed_mixes_in_the_accumulator() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            zero_randomness(&raffle);
+            let first = raffle.randomness().unwrap();
+            assert!(raffle.used_fallback);
+
+            raffle.entropy_accumulator = [1; 32];
+            zero_randomness(&raffle);
+            let second = raffle.randomness().unwrap();
+            assert_ne!(first, second);
+        }
+
+        #[ink::test]
+        fn is_winner_is_true_only_for_drawn_winners() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert!((0..5).all(|n| !raffle.is_winner(player(n))));
+            draw_to_finish(&mut raffle);
+            let winners = (0..5).filter(|n| raffle.is_winner(player(*n))).count();
+            assert_eq!(winners, 2);
+            assert!(!raffle.is_winner(accounts().eve));
+        }
+
+        #[ink::test]
+        fn self_excluded_accounts_cannot_play() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), 0);
+            raffle.exclude_myself();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::SelfExcluded));
+            set_caller(player(0), 0);
+            assert_eq!(raffle.can_i_play(), Err(Error::SelfExcluded));
+
+            // the exclusion outlives the round.
+            (1..6).for_each(|n| play(&mut raffle, player(n), MIN_DEPOSIT).unwrap());
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            settle_and_reset(&mut raffle);
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::SelfExcluded));
+            assert_eq!(play(&mut raffle, player(1), MIN_DEPOSIT), Ok(()));
+        }
+
+        #[ink::test]
+        fn average_deposit_divides_the_pot_by_the_participants() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.average_deposit(), 0);
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(1), 2 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(2), 6 * MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.average_deposit(), 3 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn threshold_reached_fires_once_on_the_last_needed_player() {
+            let mut raffle = new_raffle();
+            let reached = || events().into_iter().filter(|e| matches!(e, Event::ThresholdReached(_))).count();
+            fill(&mut raffle, 4);
+            assert_eq!(reached(), 0);
+            assert!(!raffle.threshold_reached);
+
+            advance_by(100);
+            play(&mut raffle, player(4), MIN_DEPOSIT).unwrap();
+            assert_eq!(reached(), 1);
+            assert!(matches!(
+                events().iter().rev().find(|e| matches!(e, Event::ThresholdReached(_))),
+                Some(Event::ThresholdReached(ThresholdReached { at: 100, players: 5 }))
+            ));
+
+            play(&mut raffle, player(5), MIN_DEPOSIT).unwrap();
+            set_caller(player(5), 0);
+            raffle.withdraw_entry().unwrap();
+            play(&mut raffle, player(6), MIN_DEPOSIT).unwrap();
+            assert_eq!(reached(), 1);
+        }
+
+        #[ink::test]
+        fn pot_cap_closes_entries_once_reached() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_pot_cap(Some(3 * MIN_DEPOSIT)).unwrap();
+            play(&mut raffle, player(0), 2 * MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.pot_remaining_to_cap(), Some(MIN_DEPOSIT));
+
+            assert_eq!(play(&mut raffle, player(1), 2 * MIN_DEPOSIT), Err(Error::PotCapReached));
+            set_caller(player(0), 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::PotCapReached));
+
+            assert_eq!(play(&mut raffle, player(1), MIN_DEPOSIT), Ok(()));
+            assert_eq!(raffle.pot_remaining_to_cap(), Some(0));
+            set_caller(player(2), 0);
+            assert_eq!(raffle.can_i_play(), Err(Error::PotCapReached));
+            assert!(!raffle.entries_open());
+            assert_eq!(play(&mut raffle, player(2), MIN_DEPOSIT), Err(Error::PotCapReached));
+            assert_eq!(raffle.total_pot(), 3 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn verify_draw_rejects_a_tampered_record() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            assert!(raffle.verify_draw(0));
+            assert!(raffle.verify_draw(1));
+            assert!(!raffle.verify_draw(2));
+
+            let mut record = raffle.draw_record(0).unwrap();
+            record.pool.rotate_left(1);
+            raffle.draw_records.set(0, record).unwrap();
+            assert!(!raffle.verify_draw(0));
+        }
+
+        #[ink::test]
+        fn missed_soft_cap_refunds_instead_of_drawing() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_soft_cap(Some(10 * MIN_DEPOSIT)).unwrap();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.cap_status(), CapStatus::Below);
+
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert!(raffle.refunds_enabled);
+            assert!(raffle.winners.is_empty());
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT), Err(Error::RefundsEnabled));
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.claim_refund(), Ok(()));
+            assert_eq!(balance_of(player(0)), MIN_DEPOSIT);
+            assert!(!raffle.is_candidate(player(0)));
+            assert_eq!(raffle.claim_refund(), Err(Error::HasNotPlayed));
+        }
+
+        #[ink::test]
+        fn every_draw_path_checks_the_soft_cap() {
+            let below_soft_cap = || {
+                let mut raffle = new_raffle();
+                as_owner();
+                raffle.set_soft_cap(Some(10 * MIN_DEPOSIT)).unwrap();
+                raffle.set_auto_draw(true).unwrap();
+                ready_to_draw(&mut raffle);
+                raffle
+            };
+
+            let mut raffle = below_soft_cap();
+            assert_eq!(raffle.poke(), Ok(()));
+            assert!(raffle.refunds_enabled && raffle.winners.is_empty());
+
+            let mut raffle = below_soft_cap();
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT), Ok(()));
+            assert!(raffle.refunds_enabled && raffle.winners.is_empty());
+        }
+
+        #[ink::test]
+        fn met_soft_cap_draws_and_the_hard_cap_stops_entries() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_soft_cap(Some(5 * MIN_DEPOSIT)).unwrap();
+            raffle.set_pot_cap(Some(6 * MIN_DEPOSIT)).unwrap();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.cap_status(), CapStatus::Met);
+
+            play(&mut raffle, player(5), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.cap_status(), CapStatus::Reached);
+            assert_eq!(play(&mut raffle, player(6), MIN_DEPOSIT), Err(Error::PotCapReached));
+
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert!(!raffle.refunds_enabled);
+            assert_eq!(raffle.winners.len(), 1);
+        }
+
+        #[ink::test]
+        fn play_for_enters_every_account_with_an_even_share() {
+            let mut raffle = new_raffle();
+            let employees: Vec<AccountId> = (0..5).map(player).collect();
+            set_caller(accounts().frank, 5 * MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(employees.clone()), Ok(()));
+
+            for who in employees.iter() {
+                assert!(raffle.is_candidate(*who));
+                assert_eq!(raffle.participants.get(who), Some(&MIN_DEPOSIT));
+            }
+            assert_eq!(raffle.participants.len(), 5);
+            assert!(!raffle.participants.contains_key(&accounts().frank));
+        }
+
+        #[ink::test]
+        fn play_for_rejects_bad_batches() {
+            let mut raffle = new_raffle();
+            set_caller(accounts().frank, MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(Vec::new()), Err(Error::InvalidBatchSize));
+
+            let too_many: Vec<AccountId> = (0..MAX_BATCH_SIZE as u8 + 1).map(player).collect();
+            set_caller(accounts().frank, too_many.len() as Balance * MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(too_many), Err(Error::InvalidBatchSize));
+
+            set_caller(accounts().frank, 2 * MIN_DEPOSIT + 1);
+            assert_eq!(raffle.play_for(vec![player(0), player(1)]), Err(Error::InvalidDepositAmount));
+
+            set_caller(accounts().frank, 2 * (MIN_DEPOSIT - 1));
+            assert_eq!(raffle.play_for(vec![player(0), player(1)]), Err(Error::InvalidDepositAmount));
+
+            set_caller(accounts().frank, 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(vec![player(0), player(0)]), Err(Error::DuplicateInBatch));
+            assert!(raffle.participants.is_empty());
+
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            set_caller(accounts().frank, 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(vec![player(0), player(1)]), Err(Error::EntryTokenRequired));
+        }
+
+        #[ink::test]
+        fn creation_time_and_block_are_recorded() {
+            advance_by(3 * 5);
+            let (at, block) = (now(), ink_env::block_number::<DefaultEnvironment>().unwrap());
+            let raffle = new_raffle();
+            assert_eq!(raffle.created_at_time(), at);
+            assert_eq!(raffle.created_at_block(), block);
+
+            advance_by(10);
+            assert_eq!(raffle.created_at_time(), at);
+            assert_eq!(raffle.elapsed_since_start(), now() - at);
+        }
+
+        #[ink::test]
+        fn pick_rejects_the_biased_tail() {
+            let raffle = new_raffle();
+            // a quarter of the 32-bit values fall in the tail, which naive modulo
+            // folds onto the lowest third of the indexes.
+            let len = 3usize << 30;
+            let trials = 2000;
+            let (mut uniform, mut naive) = (0, 0);
+            for i in 0..trials as u32 {
+                let randomness = Hash::from(blake2x256(&i));
+                if raffle.pick(&randomness, len) < 1 << 30 {
+                    uniform += 1;
+                }
+                let bytes = randomness.as_ref();
+                if (u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize % len) < 1 << 30 {
+                    naive += 1;
+                }
+            }
+
+            let (uniform, naive) = (uniform as f64 / trials as f64, naive as f64 / trials as f64);
+            assert!((0.30..0.37).contains(&uniform), "uniform share {}", uniform);
+            assert!(naive > 0.45, "naive share {}", naive);
+        }
+
+        #[ink::test]
+        fn pick_skips_rejected_values_and_rehashes_when_all_are() {
+            let raffle = new_raffle();
+            let len = 3usize << 30;
+
+            let mut bytes = [0xff; 32];
+            bytes[4..8].copy_from_slice(&7u32.to_le_bytes());
+            assert_eq!(raffle.pick(&Hash::from(bytes), len), 7);
+
+            let rejected = [0xff; 32];
+            let picked = raffle.pick(&Hash::from(rejected), len);
+            assert!(picked < len);
+            assert_eq!(picked, raffle.pick(&Hash::from(blake2x256(&rejected)), len));
+        }
+
+        #[ink::test]
+        fn only_the_paid_beneficiary_acknowledges_receipt() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.acknowledge_receipt(), Err(Error::NotFinished));
+
+            draw_to_finish(&mut raffle);
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.acknowledge_receipt(), Err(Error::PayoutPending));
+            raffle.withdraw().expect("withdraw");
+
+            as_owner();
+            assert_eq!(raffle.acknowledge_receipt(), Err(Error::NotBeneficiary));
+            assert!(!raffle.beneficiary_acknowledged());
+
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.acknowledge_receipt(), Ok(()));
+            assert!(raffle.beneficiary_acknowledged());
+            assert!(matches!(events().last(), Some(Event::ReceiptAcknowledged(ReceiptAcknowledged { at })) if *at == now()));
+
+            as_owner();
+            raffle.reset().expect("reset");
+            assert!(!raffle.beneficiary_acknowledged());
+        }
+
+        #[ink::test]
+        fn entry_feed_is_in_entry_order_with_timestamps() {
+            let mut raffle = new_raffle();
+            let mut expected = Vec::new();
+            for n in [3, 0, 2, 1].iter() {
+                advance_by(10);
+                play(&mut raffle, player(*n), MIN_DEPOSIT).unwrap();
+                expected.push((player(*n), now()));
+            }
+
+            assert_eq!(raffle.entry_feed_page(0, 10), expected);
+            assert_eq!(raffle.entry_feed_page(1, 2), expected[1..3].to_vec());
+            assert!(raffle.entry_feed_page(4, 10).is_empty());
+        }
+
+        #[ink::test]
+        fn lowering_min_players_enables_a_blocked_draw() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 3);
+            assert_eq!(raffle.draw(None), Err(Error::DrawNotStarted));
+
+            as_owner();
+            assert_eq!(raffle.set_min_players(WINNER_COUNT), Err(Error::InvalidMinPlayers));
+            assert_eq!(raffle.set_min_players(3 + MIN_PLAYERS_SLACK + 1), Err(Error::InvalidMinPlayers));
+            set_caller(player(0), 0);
+            assert_eq!(raffle.set_min_players(3), Err(Error::NotOwner));
+
+            as_owner();
+            assert_eq!(raffle.set_min_players(3), Ok(()));
+            let events = events();
+            assert!(events.iter().any(|e| matches!(e, Event::MinPlayersChanged(MinPlayersChanged { min_players: 3 }))));
+            assert!(matches!(events.last(), Some(Event::ThresholdReached(_))));
+            assert_ne!(raffle.draw_starts_at, 0);
+
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw(None), Ok(()));
+            as_owner();
+            assert_eq!(raffle.set_min_players(4), Err(Error::DrawStarted));
+        }
+
+        #[ink::test]
+        fn fairness_report_reflects_a_commit_reveal_raffle() {
+            let mut raffle = new_raffle();
+            as_owner();
+            let seed = [7u8; 32];
+            raffle.commit_seed(raffle.hash_seed(seed)).unwrap();
+            ready_to_draw(&mut raffle);
+            raffle.reveal_seed(seed).unwrap();
+            raffle.draw(None).unwrap();
+
+            assert_eq!(
+                raffle.fairness_report(),
+                FairnessReport {
+                    hash_algorithm: HashAlgorithm::Blake2x128,
+                    used_fallback: false,
+                    randomness_oracle: false,
+                    commit_reveal: true,
+                    draws: 1,
+                    weighting: Weighting::Equal,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn the_fallback_flag_is_cleared_for_the_next_round() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            zero_randomness(&raffle);
+            draw_to_finish(&mut raffle);
+            assert!(raffle.fairness_report().used_fallback);
+            assert_eq!(raffle.fairness_report().draws, WINNER_COUNT);
+
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            as_owner();
+            raffle.reset().unwrap();
+            assert!(!raffle.used_fallback());
+            assert_eq!(raffle.fairness_report().draws, 0);
+        }
+
+        #[ink::test]
+        fn a_draw_for_a_stale_round_is_rejected() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            let stale = raffle.round();
+            draw_to_finish(&mut raffle);
+            settle_and_reset(&mut raffle);
+
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.draw(Some(stale)), Err(Error::WrongRound));
+            assert!(raffle.winners.is_empty());
+            assert_eq!(raffle.draw(Some(raffle.round())), Ok(()));
+        }
+
+        #[ink::test]
+        fn tickets_follow_the_deposit() {
+            let mut raffle = new_raffle();
+            play(&mut raffle, player(0), 3 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(1), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.tickets_of(player(0)), 3);
+            assert_eq!(raffle.tickets_of(player(2)), 0);
+
+            set_caller(player(0), 2 * MIN_DEPOSIT);
+            raffle.increase_deposit().unwrap();
+            assert_eq!(raffle.tickets_of(player(0)), 5);
+            assert_eq!(raffle.ticket_count(), 6);
+
+            set_caller(player(1), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(raffle.tickets_of(player(1)), 0);
+            assert_eq!(raffle.ticket_count(), 5);
+
+            as_owner();
+            raffle.set_weighting(Weighting::Tickets).unwrap();
+            assert_eq!(raffle.weight_of(player(0)), raffle.tickets_of(player(0)));
+        }
+
+        #[ink::test]
+        fn equal_and_weighted_draws_pick_valid_winners() {
+            for weighting in [Weighting::Equal, Weighting::Tickets].iter() {
+                let mut raffle = new_raffle();
+                as_owner();
+                raffle.set_weighting(*weighting).unwrap();
+                fill(&mut raffle, 4);
+                play(&mut raffle, player(4), 4 * MIN_DEPOSIT).unwrap();
+                advance_to(raffle.draw_starts_at);
+                draw_to_finish(&mut raffle);
+
+                assert_eq!(raffle.winners.len(), WINNER_COUNT);
+                assert_ne!(raffle.winners[0], raffle.winners[1]);
+                for (index, record) in raffle.draw_records.iter().enumerate() {
+                    assert!(raffle.verify_draw(index as u32));
+                    if *weighting == Weighting::Equal {
+                        assert!(record.weights.is_empty());
+                    } else {
+                        let tickets: Vec<u32> = record.pool.iter().map(|who| raffle.tickets_of(*who)).collect();
+                        assert_eq!(record.weights, tickets);
+                    }
+                }
+
+                as_owner();
+                assert_eq!(raffle.set_weighting(Weighting::SqrtDeposit), Err(Error::DrawStarted));
+            }
+        }
+
+        #[ink::test]
+        fn pick_weighted_maps_the_value_onto_the_weights() {
+            let raffle = new_raffle();
+            let weights = [3, 1, 2];
+            let pick = |value: u64| {
+                let mut bytes = [0; 32];
+                bytes[..8].copy_from_slice(&value.to_le_bytes());
+                raffle.pick_weighted(&Hash::from(bytes), &weights)
+            };
+
+            assert_eq!(pick(0), 0);
+            assert_eq!(pick(2), 0);
+            assert_eq!(pick(3), 1);
+            assert_eq!(pick(4), 2);
+            assert_eq!(pick(5), 2);
+            assert_eq!(pick(6), 0);
+        }
+
+        #[ink::test]
+        fn paginated_snapshots_cover_every_participant() {
+            let mut raffle = new_raffle();
+            for n in 0..5 {
+                play(&mut raffle, player(n), (n as Balance + 1) * MIN_DEPOSIT).unwrap();
+            }
+
+            let mut archived = Vec::new();
+            let mut start = 0;
+            loop {
+                let snapshot = raffle.export_snapshot(start, 2);
+                assert_eq!(snapshot.participant_count, 5);
+                assert_eq!(snapshot.owner, accounts().alice);
+                assert_eq!(snapshot.beneficiary, accounts().eve);
+                if snapshot.participants.is_empty() {
+                    break;
+                }
+                start += snapshot.participants.len() as u32;
+                archived.extend(snapshot.participants);
+            }
+
+            archived.sort();
+            let mut expected: Vec<(AccountId, Balance)> = raffle.participants.iter().map(|(who, d)| (*who, *d)).collect();
+            expected.sort();
+            assert_eq!(archived, expected);
+            assert_eq!(archived.len(), 5);
+        }
+
+        #[ink::test]
+        fn am_i_owner_is_only_true_for_the_owner() {
+            let raffle = new_raffle();
+            as_owner();
+            assert!(raffle.am_i_owner());
+            set_caller(accounts().eve, 0);
+            assert!(!raffle.am_i_owner());
+            set_caller(player(0), 0);
+            assert!(!raffle.am_i_owner());
+        }
+
+        #[ink::test]
+        fn sqrt_weights_are_the_integer_square_root_of_the_deposit() {
+            for (n, root) in [(0, 0), (1, 1), (3, 1), (4, 2), (15, 3), (16, 4), (1 << 40, 1 << 20)].iter() {
+                assert_eq!(Raffle::isqrt(*n), *root);
+            }
+            assert_eq!(Raffle::isqrt(MAX_DEPOSIT - 1), 9_999_999);
+
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_weighting(Weighting::SqrtDeposit).unwrap();
+            fill(&mut raffle, 4);
+            play(&mut raffle, player(4), MAX_DEPOSIT).unwrap();
+            for n in 0..5 {
+                let deposit = raffle.participants.get(&player(n)).copied().unwrap();
+                assert_eq!(raffle.weight_of(player(n)) as Balance, Raffle::isqrt(deposit));
+            }
+
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            let record = raffle.draw_records.get(0).unwrap();
+            assert!(raffle.verify_draw(0));
+            let weights: Vec<u32> = record.pool.iter().map(|who| raffle.weight_of(*who)).collect();
+            assert_eq!(record.weights, weights);
+
+            as_owner();
+            assert_eq!(raffle.set_weighting(Weighting::Equal), Err(Error::DrawStarted));
+        }
+
+        #[ink::test]
+        fn total_candidate_weight_sums_each_weighting() {
+            let mut raffle = new_raffle();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(1), 4 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(2), MAX_DEPOSIT).unwrap();
+            as_owner();
+
+            assert_eq!(raffle.total_candidate_weight(), 3);
+
+            raffle.set_weighting(Weighting::Tickets).unwrap();
+            assert_eq!(raffle.total_candidate_weight(), 1 + 4 + 10);
+
+            raffle.set_weighting(Weighting::SqrtDeposit).unwrap();
+            let roots = Raffle::isqrt(MIN_DEPOSIT) + Raffle::isqrt(4 * MIN_DEPOSIT) + Raffle::isqrt(MAX_DEPOSIT);
+            assert_eq!(raffle.total_candidate_weight(), roots);
+        }
+
+        #[ink::test]
+        fn a_failed_cancel_refund_can_be_retried() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 3);
+            play(&mut raffle, player(3), 2 * MIN_DEPOSIT).unwrap();
+            // one unit short of the deposits, so only the last refund fails.
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), 5 * MIN_DEPOSIT - 1).unwrap();
+
+            as_owner();
+            assert_eq!(raffle.cancel(), Ok(()));
+            assert_eq!(raffle.draw_status(), DrawStatus::Refunding);
+
+            let failed: Vec<AccountId> = (0..4).map(player).filter(|who| raffle.failed_refund(*who) > 0).collect();
+            assert_eq!(failed.len(), 1);
+            let failed = failed[0];
+            let owed = raffle.failed_refund(failed);
+            for n in 0..4 {
+                let deposit = if n == 3 { 2 * MIN_DEPOSIT } else { MIN_DEPOSIT };
+                if player(n) != failed {
+                    assert_eq!(balance_of(player(n)), deposit);
+                    assert_eq!(raffle.refunded_of(player(n)), deposit);
+                } else {
+                    assert_eq!(owed, deposit);
+                }
+            }
+
+            set_caller(failed, 0);
+            assert_eq!(raffle.retry_refund(), Err(Error::TransferFailed));
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), owed).unwrap();
+            assert_eq!(raffle.retry_refund(), Ok(()));
+            assert_eq!(balance_of(failed), owed);
+            assert_eq!(raffle.refunded_of(failed), owed);
+            assert_eq!(raffle.failed_refund(failed), 0);
+            assert_eq!(raffle.retry_refund(), Err(Error::NothingToWithdraw));
+        }
+
+        #[ink::test]
+        fn shuffle_reorders_the_candidates_once_per_round() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 20);
+            let entered: Vec<AccountId> = raffle.candidates.iter().copied().collect();
+
+            raffle.shuffle_candidates(&Hash::from([1u8; 32]));
+            let shuffled: Vec<AccountId> = raffle.candidates.iter().copied().collect();
+            assert_ne!(shuffled, entered);
+            let (mut sorted, mut expected) = (shuffled.clone(), entered.clone());
+            sorted.sort();
+            expected.sort();
+            assert_eq!(sorted, expected);
+            assert!(shuffled.iter().all(|who| raffle.is_candidate(*who)));
+
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_stable_removal(true).unwrap();
+            raffle.set_shuffle_before_draw(true).unwrap();
+            fill(&mut raffle, 20);
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            assert!(raffle.shuffled);
+            let after_first: Vec<AccountId> = raffle.candidates.iter().copied().collect();
+            let unshuffled: Vec<AccountId> = entered.iter().copied().filter(|who| *who != raffle.winners[0]).collect();
+            assert_ne!(after_first, unshuffled);
+            assert_eq!(after_first.len() + 1, entered.len());
+
+            // the second draw only removes its winner from the shuffled order.
+            raffle.draw(None).unwrap();
+            let remaining: Vec<AccountId> = after_first.iter().copied().filter(|who| *who != raffle.winners[1]).collect();
+            assert_eq!(raffle.candidates.iter().copied().collect::<Vec<_>>(), remaining);
+        }
+
+        #[ink::test]
+        fn all_winners_returns_a_window_of_rounds() {
+            let mut raffle = new_raffle();
+            let mut history = Vec::new();
+            for _ in 0..3 {
+                ready_to_draw(&mut raffle);
+                draw_to_finish(&mut raffle);
+                history.push((raffle.round(), raffle.winners.iter().copied().collect::<Vec<_>>()));
+                settle_and_reset(&mut raffle);
+            }
+
+            assert_eq!(raffle.all_winners(history[1].0, 2), history[1..].to_vec());
+            assert_eq!(raffle.all_winners(history[0].0, 1), history[..1].to_vec());
+            assert_eq!(raffle.all_winners(0, u32::MAX), history);
+            assert!(raffle.all_winners(raffle.round(), 5).is_empty());
+        }
+
+        #[ink::test]
+        fn payouts_leave_the_reserve_in_the_contract() {
+            let mut raffle = new_raffle();
+            let minimum_balance = ink_env::minimum_balance::<DefaultEnvironment>().unwrap();
+            assert_eq!(raffle.reserve(), minimum_balance);
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            assert_eq!(balance_of(contract_id()), raffle.reserve());
+            assert_eq!(balance_of(accounts().eve), 5 * MIN_DEPOSIT);
+
+            as_owner();
+            raffle.reset().unwrap();
+            assert_eq!(raffle.set_reserve(MIN_DEPOSIT), Ok(()));
+            let reserve = raffle.reserve();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            // the old reserve is free to pay out, the new one is held back.
+            assert_eq!(balance_of(contract_id()), reserve);
+            assert_eq!(raffle.pending_withdrawal(), reserve - minimum_balance);
+            set_caller(player(0), 0);
+            assert_eq!(raffle.set_reserve(0), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn a_new_draw_gap_is_enforced_on_the_next_draw() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            as_owner();
+            assert_eq!(raffle.set_draw_gap(MAX_DRAW_GAP + 1), Err(Error::InvalidDrawGap));
+            assert_eq!(raffle.set_draw_gap(100), Ok(()));
+            assert!(matches!(events().last(), Some(Event::DrawGapChanged(DrawGapChanged { gap: 100 }))));
+            assert_eq!(raffle.draw_gap(), 100);
+
+            raffle.draw(None).unwrap();
+            let drawn_at = now();
+            assert_eq!(raffle.draw(None), Err(Error::DrawTooSoon));
+            advance_to(drawn_at + 95);
+            assert_eq!(raffle.draw(None), Err(Error::DrawTooSoon));
+            advance_to(drawn_at + 100);
+            assert_eq!(raffle.draw(None), Ok(()));
+
+            as_owner();
+            assert_eq!(raffle.set_draw_gap(0), Err(Error::Finished));
+        }
+
+        #[ink::test]
+        fn play_for_rejects_duplicates_and_accounts_that_played() {
+            let mut raffle = new_raffle();
+            set_caller(accounts().frank, 3 * MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(vec![player(0), player(1), player(0)]), Err(Error::DuplicateInBatch));
+            assert!(raffle.participants.is_empty());
+
+            play(&mut raffle, player(1), MIN_DEPOSIT).unwrap();
+            set_caller(accounts().frank, 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.play_for(vec![player(0), player(1)]), Err(Error::HasPlayed));
+            assert!(!raffle.participants.contains_key(&player(0)));
+            assert_eq!(raffle.tickets_of(player(1)), 1);
+        }
+
+        #[ink::test]
+        fn close_and_draw_closes_entries_and_draws_for_the_owner() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_manual_close(true).unwrap();
+            fill(&mut raffle, 5);
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.close_and_draw(), Err(Error::NotOwner));
+            as_owner();
+            assert_eq!(raffle.close_and_draw(), Err(Error::DrawNotStarted));
+            assert!(!raffle.entries_closed());
+
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw(None), Err(Error::EntriesOpen));
+            assert_eq!(raffle.close_and_draw(), Ok(()));
+            assert!(raffle.entries_closed());
+            assert_eq!(raffle.winners.len(), 1);
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT), Err(Error::EntriesClosed));
+        }
+
+        #[ink::test]
+        fn fallback_use_is_reported_per_draw() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            raffle.draw(None).unwrap();
+
+            test::advance_block::<DefaultEnvironment>().unwrap();
+            zero_randomness(&raffle);
+            raffle.draw(None).unwrap();
+
+            assert!(!raffle.draw_used_fallback(0));
+            assert!(raffle.draw_used_fallback(1));
+            assert!(!raffle.draw_used_fallback(2));
+            assert_eq!(raffle.draw_record(1).map(|r| r.used_fallback), Some(true));
+        }
+
+        #[ink::test]
+        fn a_revealed_entry_joins_the_raffle() {
+            let mut raffle = new_raffle();
+            let nonce = [9u8; 32];
+            set_caller(player(0), 2 * MIN_DEPOSIT);
+            let commitment = raffle.hash_entry(player(0), nonce);
+            assert_eq!(raffle.commit_entry(commitment), Ok(()));
+            assert_eq!(raffle.commit_entry(commitment), Err(Error::EntryAlreadyCommitted));
+            assert!(!raffle.participants.contains_key(&player(0)));
+            assert!(!raffle.is_candidate(player(0)));
+
+            set_caller(player(1), 0);
+            assert_eq!(raffle.reveal_entry(nonce), Err(Error::NoEntryCommitted));
+            set_caller(player(0), 0);
+            assert_eq!(raffle.reveal_entry([8u8; 32]), Err(Error::InvalidEntryReveal));
+            assert_eq!(raffle.reveal_entry(nonce), Ok(()));
+            assert_eq!(raffle.participants.get(&player(0)), Some(&(2 * MIN_DEPOSIT)));
+            assert!(raffle.is_candidate(player(0)));
+            assert_eq!(raffle.reveal_entry(nonce), Err(Error::NoEntryCommitted));
+        }
+
+        #[ink::test]
+        fn an_unrevealed_entry_is_forfeited_or_refunded_after_the_first_draw() {
+            for forfeit in [true, false].iter() {
+                let mut raffle = new_raffle();
+                as_owner();
+                raffle.set_forfeit_unrevealed_entries(*forfeit).unwrap();
+                set_caller(player(9), MIN_DEPOSIT);
+                raffle.commit_entry(raffle.hash_entry(player(9), [9u8; 32])).unwrap();
+                assert_eq!(raffle.settle_entry_commitment(player(9)), Err(Error::RevealOpen));
+
+                ready_to_draw(&mut raffle);
+                raffle.draw(None).unwrap();
+                set_caller(player(9), 0);
+                assert_eq!(raffle.reveal_entry([9u8; 32]), Err(Error::DrawStarted));
+
+                let pending = raffle.pending_withdrawal();
+                set_caller(player(0), 0);
+                assert_eq!(raffle.settle_entry_commitment(player(9)), Ok(()));
+                if *forfeit {
+                    assert_eq!(raffle.pending_withdrawal(), pending + MIN_DEPOSIT);
+                    assert_eq!(balance_of(player(9)), 0);
+                } else {
+                    assert_eq!(raffle.pending_withdrawal(), pending);
+                    assert_eq!(balance_of(player(9)), MIN_DEPOSIT);
+                    assert_eq!(raffle.refunded_of(player(9)), MIN_DEPOSIT);
+                }
+                assert_eq!(raffle.settle_entry_commitment(player(9)), Err(Error::NoEntryCommitted));
+            }
+        }
+
+        #[ink::test]
+        fn beneficiary_share_goes_to_the_payout_recipient() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.beneficiary_share(accounts().eve), Some(0));
+            assert_eq!(raffle.beneficiary_share(accounts().bob), None);
+
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(1), 3 * MIN_DEPOSIT).unwrap();
+            set_caller(accounts().frank, MIN_DEPOSIT);
+            raffle.donate().unwrap();
+
+            assert_eq!(raffle.beneficiary_share(accounts().eve), Some(5 * MIN_DEPOSIT));
+            assert_eq!(raffle.beneficiary_share(player(0)), None);
+        }
+
+        #[ink::test]
+        fn an_entry_of_the_required_amount_succeeds() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), 0);
+            let amount = raffle.required_entry_amount();
+            assert_eq!(amount, MIN_DEPOSIT);
+            assert_eq!(play(&mut raffle, player(0), amount - 1), Err(Error::InvalidDepositAmount));
+            assert_eq!(play(&mut raffle, player(0), amount), Ok(()));
+            assert_eq!(raffle.required_entry_amount(), 0);
+
+            as_owner();
+            raffle.set_pot_cap(Some(amount + MIN_DEPOSIT - 1)).unwrap();
+            set_caller(player(1), 0);
+            assert_eq!(raffle.required_entry_amount(), 0);
+        }
+
+        #[ink::test]
+        fn contributions_are_capped_across_entries_and_increases() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_max_contribution_per_account(Some(3 * MIN_DEPOSIT)).unwrap();
+
+            play(&mut raffle, player(0), 2 * MIN_DEPOSIT).unwrap();
+            set_caller(player(0), 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::ContributionCapExceeded));
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Ok(()));
+
+            // withdrawn deposits still count towards the cap.
+            set_caller(player(0), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::ContributionCapExceeded));
+            assert_eq!(play(&mut raffle, player(1), 3 * MIN_DEPOSIT), Ok(()));
+
+            as_owner();
+            raffle.set_max_contribution_per_account(None).unwrap();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Ok(()));
+        }
+
+        #[ink::test]
+        fn is_terminal_only_once_everything_is_settled() {
+            let mut raffle = new_raffle();
+            set_caller(player(9), MIN_DEPOSIT);
+            raffle.commit_entry(raffle.hash_entry(player(9), [9u8; 32])).unwrap();
+            ready_to_draw(&mut raffle);
+            assert!(!raffle.is_terminal());
+
+            draw_to_finish(&mut raffle);
+            assert!(raffle.finished());
+            assert!(!raffle.is_terminal());
+
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            assert!(!raffle.is_terminal());
+
+            raffle.settle_entry_commitment(player(9)).unwrap();
+            assert!(raffle.is_terminal());
+        }
+
+        #[ink::test]
+        fn overpayment_surplus_goes_to_the_refund_address() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), MAX_DEPOSIT + 5);
+            assert_eq!(raffle.play_with_refund_to(AccountId::from([0u8; 32])), Err(Error::InvalidRefundAddress));
+
+            assert_eq!(raffle.play_with_refund_to(accounts().frank), Ok(()));
+            assert_eq!(balance_of(accounts().frank), 5);
+            assert_eq!(balance_of(player(0)), 0);
+            assert_eq!(raffle.participants.get(&player(0)), Some(&MAX_DEPOSIT));
+
+            set_caller(player(1), MIN_DEPOSIT);
+            assert_eq!(raffle.play_with_refund_to(accounts().frank), Ok(()));
+            assert_eq!(balance_of(accounts().frank), 5);
+            assert_eq!(raffle.participants.get(&player(1)), Some(&MIN_DEPOSIT));
+        }
+
+        #[ink::test]
+        fn last_draw_randomness_is_what_picked_the_winner() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.last_draw_randomness(), None);
+
+            raffle.draw(None).unwrap();
+            let randomness = raffle.last_draw_randomness().expect("randomness");
+            let record = raffle.draw_record(0).unwrap();
+            assert_eq!(randomness, record.randomness);
+            assert_eq!(record.pool[raffle.pick(&randomness, record.pool.len())], raffle.winners[0]);
+
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.last_draw_randomness(), raffle.draw_record(1).map(|r| r.randomness));
+        }
+
+        #[ink::test]
+        fn winner_beneficiary_policies() {
+            // the top donors can't win, leaving the beneficiary and, with `extra`, player(4).
+            let drawable_beneficiary = |policy: WinnerBeneficiaryPolicy, extra: bool| {
+                let mut raffle = new_raffle();
+                as_owner();
+                raffle.set_winner_beneficiary_policy(policy).unwrap();
+                raffle.set_exclude_top_donor_from_winning(true).unwrap();
+                for n in 0..4 {
+                    play(&mut raffle, player(n), 2 * MIN_DEPOSIT).unwrap();
+                }
+                play(&mut raffle, accounts().eve, MIN_DEPOSIT).unwrap();
+                if extra {
+                    play(&mut raffle, player(4), MIN_DEPOSIT).unwrap();
+                }
+                advance_to(raffle.draw_starts_at);
+                raffle
+            };
+
+            let mut raffle = drawable_beneficiary(WinnerBeneficiaryPolicy::Allow, false);
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert_eq!(raffle.winners_page(0, 2), vec![accounts().eve]);
+
+            let mut raffle = drawable_beneficiary(WinnerBeneficiaryPolicy::Forfeit, false);
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert!(raffle.winners.is_empty());
+            assert_eq!(raffle.draw_record(0).map(|r| r.drawn), Some(accounts().eve));
+            assert!(!raffle.is_candidate(accounts().eve));
+            assert!(events()
+                .iter()
+                .any(|e| matches!(e, Event::WinForfeited(WinForfeited { who }) if *who == accounts().eve)));
+            // no one else could be drawn, so the raffle ended without a winner.
+            assert!(raffle.finished());
+
+            let mut raffle = drawable_beneficiary(WinnerBeneficiaryPolicy::Reroll, true);
+            assert_eq!(raffle.winner_beneficiary_policy, WinnerBeneficiaryPolicy::Reroll);
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert_eq!(raffle.winners_page(0, 2), vec![player(4)]);
+            assert_eq!(raffle.draw_record(0).map(|r| r.pool), Some(vec![player(4)]));
+            assert!(raffle.is_candidate(accounts().eve));
+        }
+
+        #[ink::test]
+        fn refunded_of_matches_the_cancelled_deposits() {
+            let mut raffle = new_raffle();
+            for n in 0..3 {
+                play(&mut raffle, player(n), (n as Balance + 1) * MIN_DEPOSIT).unwrap();
+            }
+            assert_eq!(raffle.refunded_of(player(0)), 0);
+
+            as_owner();
+            raffle.cancel().unwrap();
+            for n in 0..3 {
+                assert_eq!(raffle.refunded_of(player(n)), (n as Balance + 1) * MIN_DEPOSIT);
+                assert_eq!(balance_of(player(n)), raffle.refunded_of(player(n)));
+            }
+            assert_eq!(raffle.refunded_of(player(3)), 0);
+        }
+
+        #[ink::test]
+        fn draws_per_block_are_limited() {
+            let mut raffle = new_raffle();
+            as_owner();
+            assert_eq!(raffle.set_max_draws_per_block(0), Err(Error::InvalidDrawRateLimit));
+            raffle.set_max_draws_per_block(1).unwrap();
+            ready_to_draw(&mut raffle);
+
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert_eq!(raffle.draw(None), Err(Error::DrawRateLimited));
+            assert_eq!(raffle.winners.len(), 1);
+
+            test::advance_block::<DefaultEnvironment>().unwrap();
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert_eq!(raffle.winners.len(), 2);
+        }
+
+        #[ink::test]
+        fn entries_open_reflects_each_blocking_condition() {
+            let mut raffle = new_raffle();
+            assert!(raffle.entries_open());
+            as_owner();
+            raffle.set_pot_cap(Some(MIN_DEPOSIT)).unwrap();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            assert!(!raffle.entries_open());
+            as_owner();
+            raffle.set_pot_cap(None).unwrap();
+            assert!(raffle.entries_open());
+            raffle.close_entries().unwrap();
+            assert!(!raffle.entries_open());
+
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_freeze_entries_at_draw_time(true).unwrap();
+            fill(&mut raffle, 5);
+            assert!(raffle.entries_open());
+            advance_to(raffle.draw_starts_at);
+            assert!(!raffle.entries_open());
+
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 1);
+            as_owner();
+            raffle.cancel().unwrap();
+            assert!(!raffle.entries_open());
+
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert!(raffle.entries_open());
+            draw_to_finish(&mut raffle);
+            assert!(!raffle.entries_open());
+        }
+
+        #[ink::test]
+        fn the_payout_can_be_withdrawn_in_parts() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            let pending = raffle.pending_withdrawal();
+            assert_eq!(pending, 5 * MIN_DEPOSIT);
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.withdraw_amount(MIN_DEPOSIT), Err(Error::NotBeneficiary));
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.withdraw_amount(pending + 1), Err(Error::AmountExceedsPending));
+
+            assert_eq!(raffle.withdraw_amount(2 * MIN_DEPOSIT), Ok(()));
+            assert_eq!(raffle.pending_withdrawal(), 3 * MIN_DEPOSIT);
+            assert_eq!(balance_of(accounts().eve), 2 * MIN_DEPOSIT);
+            assert_eq!(raffle.withdraw_amount(3 * MIN_DEPOSIT), Ok(()));
+            assert_eq!(raffle.pending_withdrawal(), 0);
+            assert_eq!(balance_of(accounts().eve), pending);
+            assert_eq!(raffle.withdraw_amount(0), Err(Error::NothingToWithdraw));
+        }
+
+        #[ink::test]
+        fn overpayments_are_logged_in_order() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), MAX_DEPOSIT + 7);
+            raffle.play_with_refund_to(player(0)).unwrap();
+            set_caller(player(1), MIN_DEPOSIT);
+            raffle.play_with_refund_to(player(1)).unwrap();
+            set_caller(player(2), MAX_DEPOSIT + 3);
+            raffle.play_with_refund_to(accounts().frank).unwrap();
+
+            assert_eq!(raffle.overpayment_log_page(0, 10), vec![(player(0), 7), (player(2), 3)]);
+            assert_eq!(raffle.overpayment_log_page(1, 1), vec![(player(2), 3)]);
+            assert!(raffle.overpayment_log_page(2, 1).is_empty());
+        }
+
+        #[ink::test]
+        fn one_winner_per_tag_keeps_tags_distinct() {
+            let tagged_raffle = |tags: &[u32]| {
+                let mut raffle = new_raffle();
+                as_owner();
+                raffle.set_one_winner_per_tag(true).unwrap();
+                for (n, tag) in tags.iter().enumerate() {
+                    set_caller(player(n as u8), MIN_DEPOSIT);
+                    raffle.play_with_tag(*tag).unwrap();
+                }
+                advance_to(raffle.draw_starts_at);
+                raffle
+            };
+
+            let mut raffle = tagged_raffle(&[7, 7, 8, 8, 8]);
+            assert_eq!(raffle.tag_of(player(0)), Some(7));
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.winners.len(), 2);
+            assert_ne!(raffle.tag_of(raffle.winners[0]), raffle.tag_of(raffle.winners[1]));
+
+            // once everyone left shares the winner's tag, the raffle finishes early.
+            let mut raffle = tagged_raffle(&[7, 7, 7, 7, 7]);
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.winners.len(), 1);
+            assert_eq!(raffle.tag_of(player(0)), Some(7));
+
+            settle_and_reset(&mut raffle);
+            assert_eq!(raffle.tag_of(player(0)), None);
+        }
+
+        #[ink::test]
+        fn refunds_clear_the_tag() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), MIN_DEPOSIT);
+            raffle.play_with_tag(7).unwrap();
+            play(&mut raffle, player(1), MIN_DEPOSIT).unwrap();
+            raffle.refunds_enabled = true;
+
+            set_caller(player(0), 0);
+            raffle.claim_refund().unwrap();
+            assert_eq!(raffle.tag_of(player(0)), None);
+        }
+
+        #[ink::test]
+        fn refund_deadline_counts_down_to_stale_refunds() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.time_until_refund_deadline(), None);
+            as_owner();
+            raffle.set_refund_deadline(Some(100)).unwrap();
+            assert_eq!(raffle.time_until_refund_deadline(), None);
+
+            ready_to_draw(&mut raffle);
+            let deadline = raffle.draw_starts_at + 100;
+            assert_eq!(raffle.time_until_refund_deadline(), Some(deadline - now()));
+            assert_eq!(raffle.enable_stale_refunds(), Err(Error::RefundDeadlineNotReached));
+
+            advance_to(deadline);
+            assert_eq!(raffle.time_until_refund_deadline(), Some(0));
+            advance_by(50);
+            assert_eq!(raffle.time_until_refund_deadline(), Some(0));
+            set_caller(player(9), 0);
+            assert_eq!(raffle.enable_stale_refunds(), Ok(()));
+            assert_eq!(raffle.time_until_refund_deadline(), None);
+
+            as_owner();
+            raffle.refunds_enabled = false;
+            raffle.set_refund_deadline(Some(Timestamp::MAX)).unwrap();
+            assert_eq!(raffle.time_until_refund_deadline(), Some(Timestamp::MAX - now()));
+        }
+
+        #[ink::test]
+        fn stale_refunds_are_rejected_once_a_winner_is_drawn() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_refund_deadline(Some(100)).unwrap();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert!(!raffle.finished());
+
+            advance_by(100);
+            assert_eq!(raffle.time_until_refund_deadline(), Some(0));
+            assert_eq!(raffle.enable_stale_refunds(), Err(Error::DrawStarted));
+            assert!(!raffle.refunds_enabled);
+        }
+
+        #[ink::test]
+        fn a_second_entry_of_an_account_is_rejected() {
+            let mut raffle = new_raffle();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            assert_eq!(play(&mut raffle, player(0), 2 * MIN_DEPOSIT), Err(Error::HasPlayed));
+
+            // `enter` re-checks in case an entry path skipped the precondition.
+            assert_eq!(raffle.enter(player(0), 2 * MIN_DEPOSIT), Err(Error::DuplicateEntry));
+            assert_eq!(raffle.participants.get(&player(0)), Some(&MIN_DEPOSIT));
+            assert_eq!(raffle.candidates.len(), 1);
+            assert_eq!(raffle.entry_feed_page(0, 10).len(), 1);
+        }
+
+        #[ink::test]
+        fn rollover_seeds_the_next_pot() {
+            let mut raffle = new_raffle();
+            as_owner();
+            assert_eq!(raffle.set_rollover_bps(BPS_DENOMINATOR + 1), Err(Error::InvalidBps));
+            raffle.set_rollover_bps(2000).unwrap();
+            assert_eq!(raffle.splits(), Splits { rollover_bps: 2000, beneficiary_bps: 8000 });
+
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            let kept = 5 * MIN_DEPOSIT / 5;
+            assert_eq!(raffle.rollover_balance(), kept);
+            assert_eq!(raffle.pending_withdrawal(), 5 * MIN_DEPOSIT - kept);
+
+            settle_and_reset(&mut raffle);
+            assert_eq!(balance_of(accounts().eve), 5 * MIN_DEPOSIT - kept);
+            assert_eq!(raffle.rollover_balance(), 0);
+            assert_eq!(raffle.donations, kept);
+            assert_eq!(balance_of(contract_id()), raffle.reserve() + kept);
+
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.beneficiary_share(accounts().eve), Some((5 * MIN_DEPOSIT + kept) * 8 / 10));
+
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            assert_eq!(raffle.set_rollover_bps(2000), Err(Error::UnsupportedForEntryToken));
+            assert_eq!(raffle.rollover_bps, 0);
+        }
+
+        #[ink::test]
+        fn oracle_health_probes_the_configured_oracle() {
+            let mut raffle = new_raffle();
+            assert!(raffle.oracle_healthy());
+
+            as_owner();
+            raffle.set_randomness_oracle(Some(accounts().django)).unwrap();
+            assert!(calls_out(|| {
+                raffle.oracle_healthy();
+            }));
+        }
+
+        #[ink::test]
+        fn every_entry_path_credits_one_coherent_record() {
+            let mut raffle = new_raffle();
+            set_caller(accounts().frank, 2 * MIN_DEPOSIT);
+            raffle.play_for(vec![player(0), player(1)]).unwrap();
+            set_caller(player(0), 2 * MIN_DEPOSIT);
+            raffle.increase_deposit().unwrap();
+            set_caller(player(2), MIN_DEPOSIT);
+            raffle.play_with_tag(1).unwrap();
+            set_caller(player(2), MIN_DEPOSIT);
+            raffle.increase_deposit().unwrap();
+
+            for (who, deposit) in [(player(0), 3 * MIN_DEPOSIT), (player(1), MIN_DEPOSIT), (player(2), 2 * MIN_DEPOSIT)].iter() {
+                assert_eq!(raffle.participants.get(who), Some(deposit));
+                assert_eq!(raffle.tickets_of(*who), Raffle::tickets_for(*deposit));
+                assert_eq!(raffle.contributions.get(who), Some(deposit));
+                assert_eq!(raffle.candidates.iter().filter(|c| *c == who).count(), 1);
+            }
+            assert_eq!(raffle.total_pot(), 6 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn draws_remaining_counts_down_to_zero() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert_eq!(raffle.draws_remaining(), WINNER_COUNT);
+            for left in (0..WINNER_COUNT).rev() {
+                raffle.draw(None).unwrap();
+                assert_eq!(raffle.draws_remaining(), left);
+            }
+            assert!(raffle.finished());
+            assert_eq!(raffle.next_winner_ordinal(), None);
+        }
+
+        #[ink::test]
+        fn deposits_must_be_a_multiple_of_the_increment() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_deposit_increment(MIN_DEPOSIT).unwrap();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT + 1), Err(Error::InvalidIncrement));
+            assert_eq!(play(&mut raffle, player(0), 2 * MIN_DEPOSIT), Ok(()));
+            set_caller(player(0), MIN_DEPOSIT / 2);
+            assert_eq!(raffle.increase_deposit(), Err(Error::InvalidIncrement));
+
+            for disabled in [0, 1].iter() {
+                as_owner();
+                raffle.set_deposit_increment(*disabled).unwrap();
+                let who = player(1 + *disabled as u8);
+                assert_eq!(play(&mut raffle, who, MIN_DEPOSIT + 1), Ok(()));
+            }
+
+            // the minimum deposit is rounded up to the next multiple.
+            as_owner();
+            raffle.set_deposit_increment(MIN_DEPOSIT * 7 / 10).unwrap();
+            set_caller(player(5), 0);
+            assert_eq!(raffle.required_entry_amount(), MIN_DEPOSIT * 14 / 10);
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT * 14 / 10), Ok(()));
+        }
+
+        #[ink::test]
+        fn event_schema_version_is_the_current_one() {
+            let raffle = new_raffle();
+            assert_eq!(raffle.event_schema_version(), EVENT_SCHEMA_VERSION);
+            assert_eq!(raffle.event_schema_version(), 1);
+        }
+
+        #[ink::test]
+        fn draw_all_at_once_derives_distinct_winners_from_one_randomness() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            let randomness = ink_env::random::<DefaultEnvironment>(&raffle.draw_seed()).unwrap();
+
+            assert_eq!(raffle.draw_all_at_once(), Ok(()));
+            assert!(raffle.finished());
+            assert_eq!(raffle.winners.len(), WINNER_COUNT);
+            assert_ne!(raffle.winners[0], raffle.winners[1]);
+            for (i, record) in raffle.draw_records.iter().enumerate() {
+                assert_eq!(record.randomness, Hash::from(blake2x256(&(randomness, i as u32))));
+                assert_eq!(record.drawn, raffle.winners[i as u32]);
+            }
+            assert!(!raffle.draw_records.get(1).unwrap().pool.contains(&raffle.winners[0]));
+        }
+
+        #[ink::test]
+        fn play_blocker_names_the_failing_precondition() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.play_blocker(player(0)), None);
+
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.play_blocker(player(0)), Some(Error::HasPlayed));
+
+            set_caller(player(1), 0);
+            raffle.exclude_myself();
+            assert_eq!(raffle.play_blocker(player(1)), Some(Error::SelfExcluded));
+
+            as_owner();
+            raffle.set_reentry_cooldown(1000).unwrap();
+            set_caller(player(0), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(raffle.play_blocker(player(0)), Some(Error::ReentryCooldown));
+            assert_eq!(raffle.play_blocker(player(2)), None);
+
+            as_owner();
+            raffle.close_entries().unwrap();
+            assert_eq!(raffle.play_blocker(player(2)), Some(Error::EntriesClosed));
+            assert_eq!(raffle.play_blocker(player(1)), Some(Error::EntriesClosed));
+        }
+
+        #[ink::test]
+        fn pot_history_records_the_pot_at_each_draw() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert!(raffle.pot_history().is_empty());
+
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.pot_history(), vec![5 * MIN_DEPOSIT]);
+
+            set_caller(accounts().frank, MIN_DEPOSIT);
+            raffle.donate().unwrap();
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.pot_history(), vec![5 * MIN_DEPOSIT, 6 * MIN_DEPOSIT]);
+            assert_eq!(raffle.pending_withdrawal(), *raffle.pot_history().last().unwrap());
+        }
+
+        #[ink::test]
+        fn deposit_bounds_in_inclusive_and_exclusive_modes() {
+            for inclusive in [true, false].iter() {
+                let mut raffle = new_raffle();
+                as_owner();
+                raffle.set_bounds_inclusive(*inclusive).unwrap();
+                let accepted = |raffle: &mut Raffle, who: u8, deposit: Balance| play(raffle, player(who), deposit).is_ok();
+
+                assert!(!accepted(&mut raffle, 0, MIN_DEPOSIT - 1));
+                assert_eq!(accepted(&mut raffle, 1, MIN_DEPOSIT), *inclusive);
+                assert!(accepted(&mut raffle, 2, MIN_DEPOSIT + 1));
+                assert!(accepted(&mut raffle, 3, MAX_DEPOSIT - 1));
+                assert_eq!(accepted(&mut raffle, 4, MAX_DEPOSIT), *inclusive);
+                assert!(!accepted(&mut raffle, 5, MAX_DEPOSIT + 1));
+            }
+        }
+
+        #[ink::test]
+        fn every_winner_is_sent_to_the_notifier() {
+            set_caller(accounts().alice, 0);
+            let mut raffle = Raffle::with_notifier(accounts().eve, Some(accounts().django));
+            assert_eq!(raffle.notifier, Some(accounts().django));
+            ready_to_draw(&mut raffle);
+            assert!(calls_out(|| raffle.draw(None).unwrap()));
+
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            assert!(!calls_out(|| raffle.draw(None).unwrap()));
+            as_owner();
+            raffle.set_notifier(Some(accounts().django)).unwrap();
+            assert!(calls_out(|| raffle.draw(None).unwrap()));
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.set_notifier(None), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn a_cancelled_commitment_returns_the_stake() {
+            let mut raffle = new_raffle();
+            set_caller(player(9), MIN_DEPOSIT);
+            raffle.commit_entry(raffle.hash_entry(player(9), [9u8; 32])).unwrap();
+
+            assert_eq!(raffle.cancel_commitment(), Ok(()));
+            assert_eq!(balance_of(player(9)), MIN_DEPOSIT);
+            assert_eq!(raffle.refunded_of(player(9)), MIN_DEPOSIT);
+            assert!(!raffle.entry_commitments.contains_key(&player(9)));
+            assert_eq!(raffle.cancel_commitment(), Err(Error::NoEntryCommitted));
+            assert_eq!(raffle.reveal_entry([9u8; 32]), Err(Error::NoEntryCommitted));
+
+            // a revealed entry has no commitment left to cancel.
+            set_caller(player(8), MIN_DEPOSIT);
+            raffle.commit_entry(raffle.hash_entry(player(8), [8u8; 32])).unwrap();
+            raffle.reveal_entry([8u8; 32]).unwrap();
+            assert_eq!(raffle.cancel_commitment(), Err(Error::NoEntryCommitted));
+
+            set_caller(player(9), MIN_DEPOSIT);
+            raffle.commit_entry(raffle.hash_entry(player(9), [9u8; 32])).unwrap();
+            ready_to_draw(&mut raffle);
+            raffle.draw(None).unwrap();
+            set_caller(player(9), 0);
+            assert_eq!(raffle.cancel_commitment(), Err(Error::DrawStarted));
+        }
+
+        #[ink::test]
+        fn splits_sum_to_the_whole_pot() {
+            let mut raffle = new_raffle();
+            for rollover_bps in [0, 1, 2500, BPS_DENOMINATOR].iter() {
+                as_owner();
+                raffle.set_rollover_bps(*rollover_bps).unwrap();
+                let splits = raffle.splits();
+                assert_eq!(splits.rollover_bps, *rollover_bps);
+                assert_eq!(splits.rollover_bps + splits.beneficiary_bps, BPS_DENOMINATOR);
+            }
+        }
+
+        #[ink::test]
+        fn top_contributor_is_the_largest_remaining_participant() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.top_contributor(), None);
+
+            play(&mut raffle, player(0), 2 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(1), 5 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(2), 3 * MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.top_contributor(), Some((player(1), 5 * MIN_DEPOSIT)));
+
+            set_caller(player(2), 3 * MIN_DEPOSIT);
+            raffle.increase_deposit().unwrap();
+            assert_eq!(raffle.top_contributor(), Some((player(2), 6 * MIN_DEPOSIT)));
+
+            set_caller(player(2), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(raffle.top_contributor(), Some((player(1), 5 * MIN_DEPOSIT)));
+        }
+
+        #[ink::test]
+        fn the_first_draw_fixes_the_pool_in_snapshot_mode() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_snapshot_on_first_draw(true).unwrap();
+            ready_to_draw(&mut raffle);
+            raffle.draw(None).unwrap();
+            let pool = raffle.draw_record(0).unwrap().pool;
+
+            assert_eq!(play(&mut raffle, player(5), MIN_DEPOSIT), Err(Error::EntriesFrozen));
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::EntriesFrozen));
+            as_owner();
+            assert_eq!(raffle.set_snapshot_on_first_draw(false), Err(Error::DrawStarted));
+
+            raffle.draw(None).unwrap();
+            let mut second: Vec<AccountId> = raffle.draw_record(1).unwrap().pool;
+            let mut expected: Vec<AccountId> = pool.into_iter().filter(|who| *who != raffle.winners[0]).collect();
+            second.sort();
+            expected.sort();
+            assert_eq!(second, expected);
+        }
+
+        #[ink::test]
+        fn can_refund_all_detects_a_shortfall() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 3);
+            assert!(raffle.can_refund_all());
+
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), 3 * MIN_DEPOSIT).unwrap();
+            assert!(!raffle.can_refund_all());
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), 3 * MIN_DEPOSIT + raffle.reserve()).unwrap();
+            assert!(raffle.can_refund_all());
+
+            raffle.failed_refunds.insert(player(9), 1);
+            assert!(!raffle.can_refund_all());
+
+            // token deposits are checked against the token balance.
+            let raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            assert!(calls_out(|| {
+                let _ = raffle.can_refund_all();
+            }));
+        }
+
+        #[ink::test]
+        fn nft_prizes_are_minted_after_the_pot_is_credited() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_prize_mode(PrizeMode::Nft { collection: accounts().django }).unwrap();
+            ready_to_draw(&mut raffle);
+            raffle.draw(None).unwrap();
+            as_owner();
+            assert_eq!(raffle.set_prize_mode(PrizeMode::NoPrize), Err(Error::DrawStarted));
+
+            assert!(calls_out(|| raffle.draw(None).unwrap()));
+            // the mint runs once the pot is credited in full to the beneficiary.
+            assert_eq!(raffle.pending_withdrawal(), 5 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn a_pending_prize_is_claimed_from_the_collection() {
+            let mut raffle = new_raffle();
+            set_caller(player(0), 0);
+            assert_eq!(raffle.claim_prize(0), Err(Error::NoPendingPrize));
+
+            raffle.pending_prizes.insert((0, player(0)), (accounts().django, 1));
+            assert_eq!(raffle.claim_prize(1), Err(Error::NoPendingPrize));
+            assert!(calls_out(|| {
+                raffle.claim_prize(0).unwrap();
+            }));
+        }
+
+        #[ink::test]
+        fn pending_prizes_of_two_rounds_are_kept_apart() {
+            let mut raffle = new_raffle();
+            // the same account won both rounds and neither mint went through.
+            raffle.pending_prizes.insert((0, player(0)), (accounts().django, 0));
+            raffle.pending_prizes.insert((1, player(0)), (accounts().frank, 1 << 32));
+            assert_eq!(raffle.pending_prizes.len(), 2);
+            assert_eq!(raffle.pending_prizes.get(&(0, player(0))), Some(&(accounts().django, 0)));
+
+            // a claim only reaches the collection of its own round.
+            raffle.prize_mode = PrizeMode::NoPrize;
+            set_caller(player(0), 0);
+            assert!(calls_out(|| {
+                let _ = raffle.claim_prize(1);
+            }));
+            assert_eq!(raffle.claim_prize(2), Err(Error::NoPendingPrize));
+        }
+
+        #[ink::test]
+        fn psp34_results_decode_like_the_standard() {
+            type MintResult = core::result::Result<(), Psp34Error>;
+            let decode = |bytes: &[u8]| <MintResult as scale::Decode>::decode(&mut &bytes[..]);
+            assert_eq!(decode(&[0]), Ok(Ok(())));
+            assert_eq!(decode(&[1, 3]), Ok(Err(Psp34Error::TokenExists)));
+            assert_eq!(decode(&[1, 0, 4, 0xAB]), Ok(Err(Psp34Error::Custom(vec![0xAB]))));
+        }
+
+        #[ink::test]
+        fn token_pot_asks_the_entry_token() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.entry_token(), None);
+            fill(&mut raffle, 2);
+            assert_eq!(raffle.token_pot(), 2 * MIN_DEPOSIT);
+
+            let token = AccountId::from([0x22; 32]);
+            let raffle = Raffle::with_entry_token(accounts().eve, token);
+            assert_eq!(raffle.entry_token(), Some(token));
+            assert!(calls_out(|| {
+                let _ = raffle.token_pot();
+            }));
+        }
+
+        #[ink::test]
+        fn an_inconsistent_candidate_is_never_recorded_as_a_winner() {
+            let mut raffle = new_raffle();
+            ready_to_draw(&mut raffle);
+            // every candidate is still listed but missing from the membership map.
+            for n in 0..5 {
+                raffle.candidate_set.take(&player(n));
+            }
+
+            assert_eq!(raffle.draw(None), Err(Error::StateInconsistent));
+            assert!(raffle.winners.is_empty());
+            assert!(raffle.draw_records.is_empty());
+            assert_eq!(raffle.candidates.len(), 5);
+        }
+
+        #[ink::test]
+        fn draw_all_cost_scales_with_candidates_and_weighting() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 5);
+            assert_eq!(raffle.estimate_draw_all_cost(), 5 + 4);
+            for n in 5..10 {
+                play(&mut raffle, player(n), MIN_DEPOSIT).unwrap();
+            }
+            assert_eq!(raffle.estimate_draw_all_cost(), 10 + 9);
+
+            as_owner();
+            raffle.set_weighting(Weighting::Tickets).unwrap();
+            assert_eq!(raffle.estimate_draw_all_cost(), 2 * (10 + 9));
+            raffle.set_shuffle_before_draw(true).unwrap();
+            assert_eq!(raffle.estimate_draw_all_cost(), 10 + 2 * (10 + 9));
+
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.estimate_draw_all_cost(), 2 * 9);
+            raffle.draw(None).unwrap();
+            assert_eq!(raffle.estimate_draw_all_cost(), 0);
+        }
+
+        #[ink::test]
+        fn rapid_actions_wait_for_the_cooldown() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_action_cooldown(100).unwrap();
+            play(&mut raffle, player(0), MIN_DEPOSIT).unwrap();
+
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Err(Error::ActionTooSoon));
+            set_caller(player(0), 0);
+            assert_eq!(raffle.withdraw_entry(), Err(Error::ActionTooSoon));
+            assert_eq!(play(&mut raffle, player(1), MIN_DEPOSIT), Ok(()));
+
+            advance_by(100);
+            set_caller(player(0), MIN_DEPOSIT);
+            assert_eq!(raffle.increase_deposit(), Ok(()));
+            advance_by(100);
+            set_caller(player(0), 0);
+            assert_eq!(raffle.withdraw_entry(), Ok(()));
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::ActionTooSoon));
+
+            // a huge cooldown saturates instead of overflowing.
+            as_owner();
+            raffle.set_action_cooldown(Timestamp::MAX).unwrap();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Err(Error::ActionTooSoon));
+            as_owner();
+            raffle.set_action_cooldown(0).unwrap();
+            assert_eq!(play(&mut raffle, player(0), MIN_DEPOSIT), Ok(()));
+        }
+
+        #[ink::test]
+        fn remaining_finalists_lists_each_drawable_account_once() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_weighting(Weighting::Tickets).unwrap();
+            fill(&mut raffle, 5);
+            play(&mut raffle, player(5), 3 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(6), MIN_DEPOSIT).unwrap();
+            set_caller(player(6), 0);
+            raffle.withdraw_entry().unwrap();
+
+            let mut finalists = raffle.remaining_finalists();
+            finalists.sort();
+            assert_eq!(finalists, (0..6).map(player).collect::<Vec<_>>());
+
+            advance_to(raffle.draw_starts_at);
+            raffle.draw(None).unwrap();
+            let finalists = raffle.remaining_finalists();
+            assert_eq!(finalists.len(), 5);
+            assert!(!finalists.contains(&raffle.winners[0]));
+            assert!(!finalists.contains(&player(6)));
+        }
+
+        #[ink::test]
+        fn explicit_finalize_settles_after_the_draws() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_explicit_finalize(true).unwrap();
+            ready_to_draw(&mut raffle);
+            as_owner();
+            assert_eq!(raffle.finalize(), Err(Error::NotAwaitingFinalize));
+
+            for _ in 0..WINNER_COUNT {
+                raffle.draw(None).unwrap();
+            }
+            assert!(!raffle.finished());
+            assert_eq!(raffle.pending_withdrawal(), 0);
+            assert!(!raffle.is_terminal());
+            assert_eq!(raffle.draw(None), Err(Error::AwaitingFinalize));
+            assert_eq!(raffle.draw_status(), DrawStatus::AwaitingFinalize);
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.finalize(), Err(Error::NotOwner));
+            as_owner();
+            assert_eq!(raffle.finalize(), Ok(()));
+            assert!(raffle.finished());
+            assert_eq!(raffle.pending_withdrawal(), 5 * MIN_DEPOSIT);
+            assert_eq!(raffle.finalize(), Err(Error::NotAwaitingFinalize));
+
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            assert!(raffle.is_terminal());
+        }
+
+        #[ink::test]
+        fn a_round_awaiting_finalize_cannot_switch_to_refunds() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_explicit_finalize(true).unwrap();
+            raffle.set_refund_deadline(Some(0)).unwrap();
+            ready_to_draw(&mut raffle);
+            for _ in 0..WINNER_COUNT {
+                raffle.draw(None).unwrap();
+            }
+            assert!(raffle.awaiting_finalize);
+
+            assert_eq!(raffle.enable_stale_refunds(), Err(Error::DrawStarted));
+            assert_eq!(play(&mut raffle, player(9), MIN_DEPOSIT), Err(Error::EntriesFrozen));
+
+            // a finalize never pays out a pot that is being refunded.
+            raffle.refunds_enabled = true;
+            as_owner();
+            assert_eq!(raffle.finalize(), Err(Error::RefundsEnabled));
+            assert!(!raffle.finished());
+        }
+
+        #[ink::test]
+        fn play_tallies_count_the_entries() {
+            let mut raffle = new_raffle();
+            fill(&mut raffle, 4);
+            play(&mut raffle, player(4), 3 * MIN_DEPOSIT).unwrap();
+            set_caller(player(0), MIN_DEPOSIT);
+            raffle.increase_deposit().unwrap();
+            set_caller(player(1), 0);
+            raffle.withdraw_entry().unwrap();
+            assert_eq!(raffle.play_tally(), (5, 7 * MIN_DEPOSIT));
+            assert_eq!(raffle.lifetime_play_tally(), (5, 7 * MIN_DEPOSIT));
+
+            play(&mut raffle, player(5), MIN_DEPOSIT).unwrap();
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            settle_and_reset(&mut raffle);
+            assert_eq!(raffle.play_tally(), (0, 0));
+            play(&mut raffle, player(0), 2 * MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.play_tally(), (1, 2 * MIN_DEPOSIT));
+            assert_eq!(raffle.lifetime_play_tally(), (7, 10 * MIN_DEPOSIT));
+        }
+
+        #[ink::test]
+        fn the_beneficiary_hands_off_its_role() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_beneficiary_controls_handoff(true).unwrap();
+            assert_eq!(raffle.set_beneficiary(accounts().frank), Err(Error::NotBeneficiary));
+
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.set_beneficiary(accounts().frank), Ok(()));
+            assert_eq!(raffle.pending_beneficiary(), Some(accounts().frank));
+            assert_eq!(raffle.get_beneficiary(), accounts().eve);
+
+            set_caller(accounts().bob, 0);
+            assert_eq!(raffle.accept_beneficiary(), Err(Error::NotPendingBeneficiary));
+            set_caller(accounts().frank, 0);
+            assert_eq!(raffle.accept_beneficiary(), Ok(()));
+            assert_eq!(raffle.get_beneficiary(), accounts().frank);
+            assert_eq!(raffle.pending_beneficiary(), None);
+        }
+
+        #[ink::test]
+        fn an_unaccepted_beneficiary_receives_nothing() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_beneficiary(accounts().frank).unwrap();
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+
+            set_caller(accounts().frank, 0);
+            assert_eq!(raffle.withdraw(), Err(Error::NotBeneficiary));
+            set_caller(accounts().eve, 0);
+            assert_eq!(raffle.withdraw(), Ok(()));
+            assert_eq!(balance_of(accounts().frank), 0);
+
+            as_owner();
+            assert_eq!(raffle.cancel_beneficiary_transfer(), Ok(()));
+            set_caller(accounts().frank, 0);
+            assert_eq!(raffle.accept_beneficiary(), Err(Error::NotPendingBeneficiary));
+        }
+
+        #[ink::test]
+        fn entries_this_block_resets_on_a_new_block() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.entries_this_block(), 0);
+            fill(&mut raffle, 3);
+            assert_eq!(raffle.entries_this_block(), 3);
+
+            test::advance_block::<DefaultEnvironment>().unwrap();
+            assert_eq!(raffle.entries_this_block(), 0);
+            play(&mut raffle, player(3), MIN_DEPOSIT).unwrap();
+            assert_eq!(raffle.entries_this_block(), 1);
+        }
+
+        #[ink::test]
+        fn small_deposits_fund_the_pot_but_never_win() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_min_winning_deposit(2 * MIN_DEPOSIT).unwrap();
+            fill(&mut raffle, 4);
+            play(&mut raffle, player(4), 2 * MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(5), 3 * MIN_DEPOSIT).unwrap();
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+
+            let mut winners: Vec<AccountId> = raffle.winners.iter().copied().collect();
+            winners.sort();
+            assert_eq!(winners, vec![player(4), player(5)]);
+            assert_eq!(raffle.pending_withdrawal(), 9 * MIN_DEPOSIT);
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            assert_eq!(balance_of(accounts().eve), 9 * MIN_DEPOSIT);
+        }
+
+        #[ink::test]
+        fn a_round_no_deposit_can_win_is_refunded() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_min_winning_deposit(2 * MIN_DEPOSIT).unwrap();
+            ready_to_draw(&mut raffle);
+
+            assert_eq!(raffle.draw(None), Ok(()));
+            assert!(raffle.refunds_enabled);
+            assert!(raffle.winners.is_empty());
+            assert!(raffle.draw_records.is_empty());
+            assert_eq!(raffle.draw_status(), DrawStatus::Refunding);
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.claim_refund(), Ok(()));
+            assert_eq!(balance_of(player(0)), MIN_DEPOSIT);
+
+            // with a single large enough deposit, that one wins and the round ends.
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_min_winning_deposit(2 * MIN_DEPOSIT).unwrap();
+            fill(&mut raffle, 4);
+            play(&mut raffle, player(4), 2 * MIN_DEPOSIT).unwrap();
+            advance_to(raffle.draw_starts_at);
+            assert_eq!(raffle.draw_all_at_once(), Ok(()));
+            assert!(raffle.finished());
+            assert_eq!(raffle.winners_page(0, 2), vec![player(4)]);
+        }
+
+        #[ink::test]
+        fn rng_commitment_status_walks_commit_then_reveal() {
+            let mut raffle = new_raffle();
+            assert_eq!(raffle.rng_commitment_status(), RngStatus::NotCommitted);
+
+            as_owner();
+            let seed = [5u8; 32];
+            let hash = raffle.hash_seed(seed);
+            raffle.commit_seed(hash).unwrap();
+            assert_eq!(raffle.rng_commitment_status(), RngStatus::Committed { hash });
+
+            raffle.reveal_seed(seed).unwrap();
+            assert_eq!(raffle.rng_commitment_status(), RngStatus::Revealed { seed });
+
+            ready_to_draw(&mut raffle);
+            draw_to_finish(&mut raffle);
+            settle_and_reset(&mut raffle);
+            assert_eq!(raffle.rng_commitment_status(), RngStatus::NotCommitted);
+        }
+
+        #[ink::test]
+        fn released_milestones_come_out_of_the_final_payout() {
+            let mut raffle = new_raffle();
+            as_owner();
+            assert_eq!(raffle.set_milestones(vec![(3 * MIN_DEPOSIT, 1000), (MIN_DEPOSIT, 1000)]), Err(Error::InvalidMilestones));
+            assert_eq!(raffle.set_milestones(vec![(MIN_DEPOSIT, 6000), (2 * MIN_DEPOSIT, 5000)]), Err(Error::InvalidMilestones));
+            raffle.set_milestones(vec![(3 * MIN_DEPOSIT, 5000)]).unwrap();
+            assert_eq!(raffle.milestones(), vec![(3 * MIN_DEPOSIT, 5000)]);
+
+            fill(&mut raffle, 2);
+            assert_eq!(raffle.release_milestone(), Err(Error::MilestoneNotReached));
+            play(&mut raffle, player(2), MIN_DEPOSIT).unwrap();
+            set_caller(player(9), 0);
+            assert_eq!(raffle.release_milestone(), Ok(()));
+            let released = 3 * MIN_DEPOSIT / 2;
+            assert_eq!(balance_of(accounts().eve), released);
+            assert_eq!(raffle.release_milestone(), Err(Error::MilestoneNotReached));
+
+            set_caller(player(0), 0);
+            assert_eq!(raffle.withdraw_entry(), Err(Error::MilestoneReleased));
+            as_owner();
+            assert_eq!(raffle.cancel(), Err(Error::MilestoneReleased));
+
+            play(&mut raffle, player(3), MIN_DEPOSIT).unwrap();
+            play(&mut raffle, player(4), MIN_DEPOSIT).unwrap();
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.pending_withdrawal(), 5 * MIN_DEPOSIT - released);
+            set_caller(accounts().eve, 0);
+            raffle.withdraw().unwrap();
+            assert_eq!(balance_of(accounts().eve), 5 * MIN_DEPOSIT);
+
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            assert_eq!(raffle.set_milestones(vec![(3 * MIN_DEPOSIT, 5000)]), Err(Error::UnsupportedForEntryToken));
+            assert!(raffle.milestones().is_empty());
+        }
+
+        #[ink::test]
+        fn uncommitted_balance_is_the_residual_after_every_obligation() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_rollover_bps(2000).unwrap();
+            set_caller(player(9), MIN_DEPOSIT);
+            raffle.commit_entry(raffle.hash_entry(player(9), [9u8; 32])).unwrap();
+            ready_to_draw(&mut raffle);
+            raffle.failed_refunds.insert(player(8), 2);
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), balance_of(contract_id()) + 2).unwrap();
+
+            // the deposits are still bound for the beneficiary.
+            assert_eq!(raffle.uncommitted_balance(), 5 * MIN_DEPOSIT);
+
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.rollover_balance(), MIN_DEPOSIT);
+            assert_eq!(raffle.pending_withdrawal(), 4 * MIN_DEPOSIT);
+            assert_eq!(raffle.uncommitted_balance(), 0);
+
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), balance_of(contract_id()) + 7).unwrap();
+            assert_eq!(raffle.uncommitted_balance(), 7);
+
+            // failed refunds of an entry token raffle are owed in the token.
+            let mut raffle = Raffle::with_entry_token(accounts().eve, AccountId::from([0x22; 32]));
+            raffle.failed_refunds.insert(player(8), 2);
+            test::set_account_balance::<DefaultEnvironment>(contract_id(), raffle.reserve() + 3).unwrap();
+            assert_eq!(raffle.uncommitted_balance(), 3);
+        }
+
+        #[ink::test]
+        fn deposit_weighted_votes_route_the_pot_to_a_charity() {
+            let mut raffle = charity_raffle();
+            set_caller(player(0), 4 * MIN_DEPOSIT);
+            raffle.play_with_vote(0).unwrap();
+            for n in 1..4 {
+                set_caller(player(n), MIN_DEPOSIT);
+                raffle.play_with_vote(1).unwrap();
+            }
+            set_caller(player(4), MIN_DEPOSIT);
+            raffle.play_with_vote(2).unwrap();
+            set_caller(player(5), MIN_DEPOSIT);
+            assert_eq!(raffle.play_with_vote(3), Err(Error::InvalidCharity));
+
+            assert_eq!(raffle.charity_tallies(), vec![4 * MIN_DEPOSIT, 3 * MIN_DEPOSIT, MIN_DEPOSIT]);
+            assert_eq!(raffle.leading_charity(), Some(accounts().bob));
+
+            advance_to(raffle.draw_starts_at);
+            draw_to_finish(&mut raffle);
+            assert_eq!(raffle.payout_recipient(), accounts().bob);
+            assert_eq!(raffle.charity_payout_pending(), 8 * MIN_DEPOSIT);
+            assert_eq!(raffle.pending_withdrawal(), 0);
+
+            set_caller(accounts().charlie, 0);
+            assert_eq!(raffle.withdraw_charity_payout(), Err(Error::NotBeneficiary));
+            set_caller(accounts().bob, 0);
+            let before = balance_of(accounts().bob);
+            assert_eq!(raffle.withdraw_charity_payout(), Ok(()));
+            assert_eq!(balance_of(accounts().bob) - before, 8 * MIN_DEPOSIT);
+            assert_eq!(raffle.charity_payout_pending(), 0);
+            assert_eq!(raffle.get_beneficiary(), accounts().eve);
+        }
+
+        #[ink::test]
+        fn tied_votes_go_to_the_first_charity_and_no_votes_to_the_beneficiary() {
+            let mut raffle = charity_raffle();
+            assert_eq!(raffle.leading_charity(), None);
+            assert_eq!(raffle.payout_recipient(), accounts().eve);
+
+            set_caller(player(0), MIN_DEPOSIT);
+            raffle.play_with_vote(2).unwrap();
+            set_caller(player(1), MIN_DEPOSIT);
+            raffle.play_with_vote(1).unwrap();
+            assert_eq!(raffle.leading_charity(), Some(accounts().charlie));
+        }
+
+        #[ink::test]
+        fn an_unrevealed_seed_falls_back_after_the_timeout() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.set_reveal_timeout(100).unwrap();
+            raffle.commit_seed(raffle.hash_seed([5u8; 32])).unwrap();
+            assert_eq!(raffle.set_reveal_timeout(0), Err(Error::SeedAlreadyCommitted));
+            ready_to_draw(&mut raffle);
+
+            assert_eq!(raffle.draw(None), Err(Error::SeedNotRevealed));
+            assert_eq!(raffle.draw_fallback(), Err(Error::RevealNotTimedOut));
+
+            advance_by(100);
+            set_caller(player(9), 0);
+            assert_eq!(raffle.draw_fallback(), Ok(()));
+            assert_eq!(raffle.winners.len(), 1);
+            assert!(raffle.used_fallback());
+            assert!(raffle.draw_used_fallback(0));
+            assert_eq!(raffle.draw(None), Err(Error::SeedNotRevealed));
+        }
+
+        #[ink::test]
+        fn without_a_timeout_there_is_no_fallback_draw() {
+            let mut raffle = new_raffle();
+            as_owner();
+            raffle.commit_seed(raffle.hash_seed([5u8; 32])).unwrap();
+            ready_to_draw(&mut raffle);
+            advance_by(1000);
+            assert_eq!(raffle.draw_fallback(), Err(Error::RevealNotTimedOut));
         }
     }
 }